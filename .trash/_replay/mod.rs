@@ -1,3 +0,0 @@
-mod metadata;
-mod replay;
-mod stats;
\ No newline at end of file
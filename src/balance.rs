@@ -0,0 +1,64 @@
+//! Splitting players into even teams
+
+/// Most players in a generals.io custom game.
+pub const MAX_PLAYERS: usize = 16;
+
+/// Searches every split of players with `stars` into `teams` teams whose
+/// sizes differ by at most one, returning the team of each player in the
+/// split with the smallest gap between the strongest and weakest team.
+pub fn balance(stars: &[f64], teams: usize) -> Vec<usize> {
+    let n = stars.len();
+    let mut capacity = vec![n / teams; teams];
+    for c in capacity.iter_mut().take(n % teams) {
+        *c += 1;
+    }
+
+    struct Search<'a> {
+        stars: &'a [f64],
+        capacity: Vec<usize>,
+        sizes: Vec<usize>,
+        totals: Vec<f64>,
+        current: Vec<usize>,
+        best: Option<(f64, Vec<usize>)>,
+    }
+
+    impl Search<'_> {
+        fn run(&mut self, player: usize) {
+            if player == self.stars.len() {
+                let max = self.totals.iter().cloned().fold(f64::MIN, f64::max);
+                let min = self.totals.iter().cloned().fold(f64::MAX, f64::min);
+                if self.best.as_ref().is_none_or(|(gap, _)| max - min < *gap) {
+                    self.best = Some((max - min, self.current.clone()));
+                }
+                return;
+            }
+            for team in 0..self.capacity.len() {
+                if self.sizes[team] == self.capacity[team] {
+                    continue;
+                }
+                self.sizes[team] += 1;
+                self.totals[team] += self.stars[player];
+                self.current.push(team);
+                self.run(player + 1);
+                self.current.pop();
+                self.totals[team] -= self.stars[player];
+                self.sizes[team] -= 1;
+                // empty teams of the same size are interchangeable
+                if self.sizes[team] == 0 && self.capacity[team + 1..].iter().all(|&c| c == self.capacity[team]) {
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut search = Search {
+        stars,
+        capacity,
+        sizes: vec![0; teams],
+        totals: vec![0.0; teams],
+        current: Vec::new(),
+        best: None,
+    };
+    search.run(0);
+    search.best.map(|(_, assignment)| assignment).unwrap_or_default()
+}
@@ -0,0 +1,61 @@
+//! Line charts
+
+use serenity::utils::Colour;
+
+use crate::replay::render::Canvas;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+/// Room for y axis labels.
+const MARGIN_LEFT: u32 = 50;
+const MARGIN: u32 = 15;
+const GRID_LINES: u32 = 5;
+
+const BACKGROUND: [u8; 3] = [0x2f, 0x31, 0x36];
+const GRID: [u8; 3] = [0x4f, 0x54, 0x5c];
+const LABEL: [u8; 3] = [0xb9, 0xbb, 0xbe];
+
+/// Plots each series of (x, y) points, all sharing the same axes.
+pub fn line_chart(series: &[(Colour, Vec<(u64, f64)>)]) -> Canvas {
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
+    canvas.fill_rect(0, 0, WIDTH, HEIGHT, BACKGROUND);
+
+    let points = series.iter().flat_map(|(_, s)| s.iter());
+    let (x_min, x_max) = points.clone().fold((u64::MAX, 0), |(lo, hi), &(x, _)| (lo.min(x), hi.max(x)));
+    let (mut y_min, mut y_max) = points.fold((f64::MAX, f64::MIN), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+    if x_min > x_max {
+        return canvas;
+    }
+
+    // pad so flat lines aren't on the border
+    let pad = ((y_max - y_min) * 0.1).max(1.0);
+    y_min = (y_min - pad).max(0.0).floor();
+    y_max = (y_max + pad).ceil();
+
+    let (plot_w, plot_h) = (WIDTH - MARGIN_LEFT - MARGIN, HEIGHT - 2 * MARGIN);
+    let to_px = |(x, y): (u64, f64)| {
+        let fx = if x_max == x_min { 0.5 } else { (x - x_min) as f64 / (x_max - x_min) as f64 };
+        let fy = (y - y_min) / (y_max - y_min);
+        ((MARGIN_LEFT as f64 + fx * plot_w as f64) as i64, (MARGIN as f64 + (1.0 - fy) * plot_h as f64) as i64)
+    };
+
+    for k in 0..=GRID_LINES {
+        let y = MARGIN + plot_h * k / GRID_LINES;
+        canvas.fill_rect(MARGIN_LEFT, y, plot_w, 1, GRID);
+        let value = y_max - (y_max - y_min) * k as f64 / GRID_LINES as f64;
+        canvas.draw_number(value.round() as u64, MARGIN_LEFT / 2, y, MARGIN_LEFT - 6, LABEL);
+    }
+
+    for (color, points) in series {
+        let color = [color.r(), color.g(), color.b()];
+        for pair in points.windows(2) {
+            canvas.draw_line(to_px(pair[0]), to_px(pair[1]), 2, color);
+        }
+        if let [point] = points.as_slice() {
+            let (x, y) = to_px(*point);
+            canvas.fill_rect((x - 2).max(0) as u32, (y - 2).max(0) as u32, 5, 5, color);
+        }
+    }
+
+    canvas
+}
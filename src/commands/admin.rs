@@ -0,0 +1,137 @@
+//! `/admin` registration overrides for the bot's owners
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::{id::GuildId, interactions::application_command::ApplicationCommandInteraction, user::User}};
+
+use crate::{embeds, palette, DB};
+use super::{owner_guild, register, Arg, Command};
+
+/// Parses the `user` and `username` options of `i`.
+fn user_and_username(i: &ApplicationCommandInteraction) -> (u64, String) {
+    let mut user = 0;
+    let mut username = String::new();
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("user", Some(value)) => user = value.as_str().unwrap().parse().unwrap_or(0),
+            ("username", Some(value)) => username.push_str(value.as_str().unwrap()),
+            _ => {}
+        }
+    }
+    (user, username)
+}
+
+/// Resyncs tier roles after a link change, if `user` is in `guild`.
+async fn resync(ctx: &Context, guild: u64, user: u64, username: Option<&str>) {
+    match ctx.http.get_member(guild, user).await {
+        Ok(member) => register::sync_roles(ctx, GuildId(guild), &member, username).await,
+        Err(e) => eprintln!("roles: failed to fetch {} in {}: {}", user, guild, e),
+    }
+}
+
+fn handle_link(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let (user, username) = user_and_username(&i);
+
+    Box::pin(async move {
+        let guild = match owner_guild(&ctx, &i, "Link Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+        if ctx.http.get_member(guild, user).await.is_err() {
+            embeds::respond(&ctx, &i, embeds::error(Some("Link Error"), format!("<@{}> isn't a member of this server", user))).await?;
+            return Ok(())
+        }
+        if !register::username_exists(&username).await? {
+            embeds::respond(&ctx, &i, embeds::error(Some("Link Error"), format!("{} is not a generals.io username", username))).await?;
+            return Ok(())
+        }
+
+        let db = DB.get().await;
+        let owner = db.get_discord(&username).await?;
+        if let Some(owner) = owner.filter(|&owner| owner != user) {
+            embeds::respond(&ctx, &i, embeds::error(Some("Link Error"), format!("{} is registered to <@{}>; unlink them first", username, owner))).await?;
+            return Ok(())
+        }
+        let previous = db.get_username(user).await?;
+        let linked = match &previous {
+            Some(_) => db.set_username(user, &username).await?,
+            None => db.add_username(user, &username).await?,
+        };
+        if !linked {
+            embeds::respond(&ctx, &i, embeds::error(Some("Link Error"), format!("{} is already registered", username))).await?;
+            return Ok(())
+        }
+        eprintln!("admin: {} linked {} to {} in {}", i.user.id, user, username, guild);
+
+        resync(&ctx, guild, user, Some(&username)).await;
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Linked")
+            .description(match previous {
+                Some(previous) => format!("<@{}> is now {} (was {})", user, username, previous),
+                None => format!("<@{}> is now {}", user, username),
+            })
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_unlink(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let (user, _) = user_and_username(&i);
+
+    Box::pin(async move {
+        let guild = match owner_guild(&ctx, &i, "Unlink Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+
+        let username = DB.get().await.remove_username(user).await?;
+        let username = match username {
+            Some(username) => username,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Unlink Error"), format!("<@{}> isn't registered", user))).await?;
+                return Ok(())
+            }
+        };
+        eprintln!("admin: {} unlinked {} from {} in {}", i.user.id, user, username, guild);
+
+        resync(&ctx, guild, user, None).await;
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Unlinked")
+            .description(format!("<@{}> is no longer linked to {}", user, username))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_ADMIN: Command = Command::group("admin", "registration overrides for the bot's owners", vec![
+        Command {
+            name: "link".into(),
+            description: "links a member to a generals.io username without verification".into(),
+            args: vec![
+                Arg { name: "user".into(), description: "member to link".into(), required: true, type_: TypeId::of::<User>() },
+                Arg { name: "username".into(), description: "generals.io username".into(), required: true, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_link,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "unlink".into(),
+            description: "removes a member's registration".into(),
+            args: vec![
+                Arg { name: "user".into(), description: "member to unlink".into(), required: true, type_: TypeId::of::<User>() },
+            ],
+            handler: handle_unlink,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
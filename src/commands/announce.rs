@@ -0,0 +1,76 @@
+//! New-game announcements and the `/announce` command
+
+use std::{collections::HashMap, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{embeds, palette, replay::metadata::Metadata, DB};
+use super::{admin_guild, Command};
+
+/// Result card for `game`, mentioning the registered players in `discords`.
+pub fn result_card(game: &Metadata, discords: &[u64], discord_of: &HashMap<String, u64>) -> CreateEmbed {
+    let players = game.ranking.iter()
+        .enumerate()
+        .map(|(k, p)| {
+            let discord = discord_of.get(&p.name.to_lowercase())
+                .or_else(|| discord_of.get(&p.current_name.to_lowercase()))
+                .filter(|d| discords.contains(d))
+                .map(|d| format!(" (<@{}>)", d))
+                .unwrap_or_default();
+            let marker = if k < game.winners() { "🏆 " } else { "" };
+            format!("**{}.** {}{} ★{}{}", k + 1, marker, p.name, p.stars.round(), discord)
+        })
+        .collect::<Vec<_>>();
+
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("{} Game Finished", game.mode()))
+        .url(format!("https://generals.io/replays/{}", game.id))
+        .description(format!("<t:{}:R>, {} turns\n\n{}", game.started / 1000, game.turns, players.join("\n")))
+        .color(palette::EMBED_GAME);
+    embed
+}
+
+fn set_channel(ctx: &Context, i: &ApplicationCommandInteraction, enabled: bool) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match admin_guild(&ctx, &i, "Announce Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+
+        DB.get().await.set_announce_channel(guild, if enabled { Some(i.channel_id.0) } else { None }).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(if enabled { "Announcements Enabled" } else { "Announcements Disabled" })
+            .description(if enabled {
+                format!("Games finished by registered members will be posted in <#{}>", i.channel_id)
+            } else {
+                "Games will no longer be announced in this server".to_string()
+            })
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_ANNOUNCE: Command = Command::group("announce", "new-game announcements", vec![
+        Command {
+            name: "here".into(),
+            description: "announces games finished by registered members in this channel".into(),
+            args: Vec::new(),
+            handler: |ctx, i| set_channel(ctx, i, true),
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "disable".into(),
+            description: "stops announcing games in this server".into(),
+            args: Vec::new(),
+            handler: |ctx, i| set_channel(ctx, i, false),
+            subcommands: Vec::new(),
+        },
+    ]);
+}
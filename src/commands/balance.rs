@@ -0,0 +1,112 @@
+//! `/balance` command
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{balance, embeds, palette, rooms, stars::{self, Mode}, DB};
+use super::{Arg, Command};
+
+/// Stars assumed for members without a linked account or stars in the mode.
+const DEFAULT_STARS: f64 = 40.0;
+
+/// Discord IDs mentioned in `text`, in order and without duplicates.
+fn mentions(text: &str) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for part in text.split("<@").skip(1) {
+        let id = part.trim_start_matches('!').split('>').next().and_then(|id| id.parse().ok());
+        if let Some(id) = id {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+fn handle_balance(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut players = String::new();
+    let mut teams = 2;
+    let mut mode = "2v2".to_string();
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("players", Some(value)) => players.push_str(value.as_str().unwrap()),
+            ("teams", Some(value)) => teams = value.as_u64().unwrap_or(2) as usize,
+            ("mode", Some(value)) => mode = value.as_str().unwrap().to_string(),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let players = mentions(&players);
+        let mode: Mode = match mode.parse() {
+            Ok(mode) => mode,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Balance Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+        if teams < 2 || players.len() < teams || players.len() > balance::MAX_PLAYERS {
+            embeds::respond(&ctx, &i, embeds::error(Some("Balance Error"),
+                format!("mention between {} and {} players, at least one per team", teams.max(2), balance::MAX_PLAYERS))).await?;
+            return Ok(())
+        }
+
+        embeds::defer(&ctx, &i).await?;
+
+        let db = DB.get().await;
+        let mut stars = Vec::new();
+        let mut names = Vec::new();
+        for &discord in &players {
+            let username = db.get_username(discord).await.map_err(|e| e.to_string())?;
+            let s = match &username {
+                Some(username) => stars::fetch(username).await.map_err(|e| e.to_string())?.get(mode, false),
+                None => None,
+            };
+            names.push(match (&username, s) {
+                (Some(username), Some(_)) => format!("<@{}> ({})", discord, username),
+                (Some(username), None) => format!("<@{}> ({}, no stars)", discord, username),
+                (None, _) => format!("<@{}> (unlinked)", discord),
+            });
+            stars.push(s.unwrap_or(DEFAULT_STARS));
+        }
+
+        let assignment = {
+            let stars = stars.clone();
+            tokio::task::spawn_blocking(move || balance::balance(&stars, teams)).await?
+        };
+
+        let code = rooms::random_code();
+        let url = rooms::room_url(&code, None, None);
+        let mut embed = CreateEmbed::default();
+        embed.title("Balanced Teams")
+            .url(&url)
+            .description(format!("**Room:** {}\nStars are {}; unlinked members count as {}★", url, mode, DEFAULT_STARS))
+            .color(palette::EMBED_GAME);
+        for team in 0..teams {
+            let members = (0..players.len()).filter(|&p| assignment[p] == team).collect::<Vec<_>>();
+            let total = members.iter().map(|&p| stars[p]).sum::<f64>();
+            embed.field(format!("Team {} ({:.0}★)", team + 1, total),
+                members.iter().map(|&p| names[p].clone()).collect::<Vec<_>>().join("\n"), true);
+        }
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_BALANCE: Command = Command {
+        name: "balance".into(),
+        description: "splits members into teams with even total stars".into(),
+        args: vec![
+            Arg { name: "players".into(), description: "members to split, as mentions".into(), required: true, type_: TypeId::of::<String>() },
+            Arg { name: "teams".into(), description: "number of teams (defaults to 2)".into(), required: false, type_: TypeId::of::<u64>() },
+            Arg { name: "mode".into(), description: "stars to balance by: ffa, duel or 2v2 (defaults to 2v2)".into(), required: false, type_: TypeId::of::<String>() },
+        ],
+        handler: handle_balance,
+        subcommands: Vec::new(),
+    };
+}
@@ -0,0 +1,126 @@
+//! `/custom` command for custom game rooms
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{embeds, palette, rooms::{random_code, Room, Speed}, DB};
+use super::{Arg, Command};
+
+/// How long a room is listed as open after it is created.
+const ROOM_LIFETIME: u64 = 2 * 60 * 60;
+const MAX_LISTED: usize = 15;
+
+/// Whether `code` is a matchmaking queue rather than a custom room.
+fn is_queue(code: &str) -> bool {
+    matches!(code, "main" | "1v1" | "2v2")
+}
+
+fn handle_create(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut code = None;
+    let mut map = None;
+    let mut speed = None;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("code", Some(value)) => code = value.as_str().map(|s| s.to_string()),
+            ("map", Some(value)) => map = value.as_str().map(|s| s.to_string()),
+            ("speed", Some(value)) => speed = value.as_str().map(|s| s.to_string()),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let speed = match speed.map(|s| s.parse::<Speed>()).transpose() {
+            Ok(speed) => speed,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Custom Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+
+        let room = Room {
+            code: code.unwrap_or_else(random_code),
+            map,
+            speed,
+            creator: i.user.id.0,
+            time: crate::unix_time(),
+        };
+        if let (Some(guild), false) = (i.guild_id, is_queue(&room.code)) {
+            DB.get().await.add_room(guild.0, &room).await?;
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title(if is_queue(&room.code) { "Queue" } else { "Custom Game" })
+            .url(room.url())
+            .description(room.description())
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_list(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Custom Error"), "rooms are only listed in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        let rooms = DB.get().await.get_rooms(guild.0, crate::unix_time().saturating_sub(ROOM_LIFETIME)).await?;
+        let mut embed = CreateEmbed::default();
+        embed.title("Open Rooms")
+            .description(if rooms.is_empty() {
+                "*no rooms created in the last two hours*".to_string()
+            } else {
+                rooms.iter()
+                    .take(MAX_LISTED)
+                    .map(|r| {
+                        let mut line = format!("[{}]({}) by <@{}> <t:{}:R>", r.code, r.url(), r.creator, r.time);
+                        if let Some(map) = &r.map {
+                            line += &format!(" — {}", map);
+                        }
+                        if let Some(speed) = r.speed {
+                            line += &format!(" — {}", speed);
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_CUSTOM: Command = Command::group("custom", "custom game rooms", vec![
+        Command {
+            name: "create".into(),
+            description: "links a custom room, or a queue with main, 1v1 or 2v2".into(),
+            args: vec![
+                Arg { name: "code".into(), description: "room code, or main, 1v1 or 2v2 (random if empty)".into(), required: false, type_: TypeId::of::<String>() },
+                Arg { name: "map".into(), description: "custom map".into(), required: false, type_: TypeId::of::<String>() },
+                Arg { name: "speed".into(), description: "game speed, e.g. 2x".into(), required: false, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_create,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "list".into(),
+            description: "lists rooms recently created in this server".into(),
+            args: Vec::new(),
+            handler: handle_list,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
@@ -0,0 +1,95 @@
+//! `/graph` command
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{chart, embeds, palette, stars::{self, Mode}, DB};
+use super::{Arg, Command};
+
+fn handle_graph(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut users = Vec::new();
+    let mut mode = "duel".to_string();
+    let mut alltime = false;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("mode", Some(value)) => mode = value.as_str().unwrap().to_string(),
+            ("alltime", Some(value)) => alltime = value.as_bool().unwrap_or(false),
+            (_, Some(value)) => users.push(value.as_str().unwrap().to_string()),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let mode: Mode = match mode.parse() {
+            Ok(mode) => mode,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Graph Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+
+        let mut usernames = Vec::new();
+        for user in &users {
+            let username = crate::resolve_username(user).await?;
+            match username {
+                Some(username) => usernames.push(username),
+                None => {
+                    embeds::respond(&ctx, &i, embeds::error(Option::<String>::None, "Discord user not registered")).await?;
+                    return Ok(())
+                }
+            }
+        }
+
+        embeds::defer(&ctx, &i).await?;
+
+        let db = DB.get().await;
+        let now = crate::unix_time();
+        let mut series = Vec::new();
+        let mut legend = Vec::new();
+        for (k, username) in usernames.iter().enumerate() {
+            let mut points = db.get_stars(username).await.map_err(|e| e.to_string())?
+                .into_iter()
+                .filter_map(|(time, s)| s.get(mode, alltime).map(|y| (time, y)))
+                .collect::<Vec<_>>();
+            // include the live value so users without history still show up
+            if let Some(y) = stars::fetch(username).await.map_err(|e| e.to_string())?.get(mode, alltime) {
+                points.push((now, y));
+            }
+
+            legend.push(format!("{} {} ({} points)", palette::PLAYER_EMOJI[k], username, points.len()));
+            series.push((palette::PLAYERS[k], points));
+        }
+
+        let first = series.iter().filter_map(|(_, s)| s.first()).map(|&(t, _)| t).min().unwrap_or(now);
+        let png = chart::line_chart(&series).to_png()?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("{} {}Stars", mode, if alltime { "All-Time " } else { "" }))
+            .description(format!("{}\n\n<t:{}:d> – <t:{}:d>", legend.join("\n"), first, now))
+            .image("attachment://graph.png")
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, Some((png, "graph.png"))).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_GRAPH: Command = Command {
+        name: "graph".into(),
+        description: "charts star history".into(),
+        args: vec![
+            Arg { name: "user".into(), description: "generals.io username or discord mention".into(), required: true, type_: TypeId::of::<String>() },
+            Arg { name: "mode".into(), description: "ffa, duel or 2v2 (defaults to duel)".into(), required: false, type_: TypeId::of::<String>() },
+            Arg { name: "alltime".into(), description: "show all-time stars".into(), required: false, type_: TypeId::of::<bool>() },
+            Arg { name: "user2".into(), description: "another user to compare".into(), required: false, type_: TypeId::of::<String>() },
+            Arg { name: "user3".into(), description: "another user to compare".into(), required: false, type_: TypeId::of::<String>() },
+            Arg { name: "user4".into(), description: "another user to compare".into(), required: false, type_: TypeId::of::<String>() },
+        ],
+        handler: handle_graph,
+        subcommands: Vec::new(),
+    };
+}
@@ -0,0 +1,131 @@
+//! `/h2h` command
+
+use std::{any::TypeId, collections::BTreeMap, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{embeds, palette, replay::{self, metadata::{self, Metadata}}};
+use super::{Arg, Command};
+
+const PAGE_SIZE: usize = 100;
+/// Most games of the first player to look through.
+const MAX_SCANNED: usize = 1000;
+const RECENT_ENCOUNTERS: usize = 5;
+
+/// Whether the players placed `a` and `b` were on the same team in `game`.
+/// 2v2 rankings list the winning pair first; custom games need the replay.
+async fn teammates(game: &Metadata, a: usize, b: usize) -> crate::Result<bool> {
+    match game.type_.as_str() {
+        "2v2" => Ok((a < game.winners()) == (b < game.winners())),
+        "custom" if game.ranking.len() > 2 => {
+            let replay = replay::fetch(&game.id).await?;
+            let team = |k: usize| {
+                let name = &game.ranking[k].name;
+                let index = replay.usernames.iter().position(|u| u.eq_ignore_ascii_case(name))?;
+                replay.teams.as_ref()?.get(index).copied()
+            };
+            Ok(matches!((team(a), team(b)), (Some(x), Some(y)) if x == y))
+        },
+        _ => Ok(false),
+    }
+}
+
+/// All games in `a`'s history that `b` also played in as an opponent, newest first.
+pub async fn shared_games(a: &str, b: &str) -> crate::Result<Vec<Metadata>> {
+    let mut shared = Vec::new();
+    let mut offset = 0;
+    while offset < MAX_SCANNED {
+        let page = metadata::fetch(a, offset, PAGE_SIZE).await?;
+        let len = page.len();
+        for game in page {
+            let (pa, pb) = match (game.placement(a), game.placement(b)) {
+                (Some(pa), Some(pb)) => (pa, pb),
+                _ => continue,
+            };
+            match teammates(&game, pa, pb).await {
+                Ok(false) => shared.push(game),
+                Ok(true) => {},
+                Err(e) => eprintln!("skipping {} in head-to-head: {}", game.id, e),
+            }
+        }
+        if len < PAGE_SIZE {
+            break;
+        }
+        offset += len;
+    }
+    Ok(shared)
+}
+
+fn handle_h2h(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let (mut a, mut b) = (String::new(), String::new());
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("a", Some(value)) => a.push_str(value.as_str().unwrap()),
+            ("b", Some(value)) => b.push_str(value.as_str().unwrap()),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let a = crate::resolve_username(&a).await?;
+        let b = crate::resolve_username(&b).await?;
+        let (a, b) = match (a, b) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                embeds::respond(&ctx, &i, embeds::error(Option::<String>::None, "Discord user not registered")).await?;
+                return Ok(())
+            }
+        };
+
+        embeds::defer(&ctx, &i).await?;
+        let games = shared_games(&a, &b).await.map_err(|e| e.to_string())?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Head to Head: {} vs {}", a, b)).color(palette::EMBED_GAME);
+        if games.is_empty() {
+            embed.description("No games played together");
+            embeds::followup(&ctx, &i, embed, None).await?;
+            return Ok(())
+        }
+
+        // a "win" is finishing ahead of the other player
+        let mut modes: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+        for game in &games {
+            let entry = modes.entry(game.mode()).or_default();
+            if game.placement(&a) < game.placement(&b) { entry.0 += 1 } else { entry.1 += 1 }
+        }
+        let average_turns = games.iter().map(|g| g.turns).sum::<u64>() as f64 / games.len() as f64;
+
+        embed.description(format!("**Games together**: {}\n**Average length**: {:.0} turns", games.len(), average_turns));
+        embed.field("Wins", modes.iter()
+            .map(|(mode, (wa, wb))| format!("**{}**: {} – {}", mode, wa, wb))
+            .collect::<Vec<_>>()
+            .join("\n"), false);
+        embed.field("Recent Encounters", games.iter()
+            .take(RECENT_ENCOUNTERS)
+            .map(|g| format!("[{}](https://generals.io/replays/{}) <t:{}:R>: {} #{}, {} #{}",
+                g.mode(), g.id, g.started / 1000,
+                a, g.placement(&a).unwrap_or(0) + 1,
+                b, g.placement(&b).unwrap_or(0) + 1))
+            .collect::<Vec<_>>()
+            .join("\n"), false);
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_H2H: Command = Command {
+        name: "h2h".into(),
+        description: "head-to-head record between two players".into(),
+        args: vec![
+            Arg { name: "a".into(), description: "generals.io username or discord mention".into(), required: true, type_: TypeId::of::<String>() },
+            Arg { name: "b".into(), description: "generals.io username or discord mention".into(), required: true, type_: TypeId::of::<String>() },
+        ],
+        handler: handle_h2h,
+        subcommands: Vec::new(),
+    };
+}
@@ -0,0 +1,186 @@
+//! `/leaderboard` command
+
+use std::{any::TypeId, collections::{HashMap, HashSet}, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{embeds, palette, ratings::Pool, stars::{Mode, Stars}, DB};
+use super::{Arg, Command};
+
+const PAGE_SIZE: usize = 15;
+/// Server ratings listed under a season's standings.
+const RATINGS_SHOWN: usize = 10;
+
+/// Usernames in `snapshot` belonging to `members`, sorted by stars, best first.
+fn rank(snapshot: &[(String, Stars)], members: &HashSet<String>, mode: Mode, alltime: bool) -> Vec<(String, f64)> {
+    let mut ranked = snapshot.iter()
+        .filter(|(username, _)| members.contains(username))
+        .filter_map(|(username, stars)| stars.get(mode, alltime).map(|s| (username.clone(), s)))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
+
+/// Responds with the standings archived at the end of `season`.
+async fn season_leaderboard(ctx: &Context, i: &ApplicationCommandInteraction, guild: u64, number: u32, mode: Mode, alltime: bool, page: usize) -> crate::Result<()> {
+    let db = DB.get().await;
+    let seasons = db.get_seasons(guild).await?;
+    let season = match seasons.into_iter().find(|s| s.number == number) {
+        Some(season) if season.archived => season,
+        Some(_) => {
+            embeds::respond(ctx, i, embeds::error(Some("Leaderboard Error"), format!("season {} hasn't ended yet", number))).await?;
+            return Ok(())
+        },
+        None => {
+            embeds::respond(ctx, i, embeds::error(Some("Leaderboard Error"), format!("there is no season {}", number))).await?;
+            return Ok(())
+        }
+    };
+
+    let snapshot = db.get_season_stars(guild, number).await?;
+    let members = snapshot.iter().map(|(u, _)| u.clone()).collect::<HashSet<_>>();
+    let standings = rank(&snapshot, &members, mode, alltime);
+    let pool = match mode {
+        Mode::Duel => Pool::Duel,
+        Mode::Ffa => Pool::Ffa,
+        Mode::M2v2 => Pool::Teams,
+    };
+    let ratings = db.get_season_ratings(guild, number, pool).await?;
+
+    let pages = standings.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages);
+    let lines = standings.iter().enumerate()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|(k, (username, stars))| format!("**{}.** {} — {:.2}", k + 1, username, stars))
+        .collect::<Vec<_>>();
+
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("Season {}: {} — {} {}Leaderboard", season.number, season.name, mode, if alltime { "All-Time " } else { "" }))
+        .description(format!("<t:{}:d> – <t:{}:d>\n\n{}", season.start, season.end,
+            if lines.is_empty() { "*no members had stars*".to_string() } else { lines.join("\n") }))
+        .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+        .color(palette::EMBED_GAME);
+    if !ratings.is_empty() {
+        embed.field(format!("{} Server Ratings", pool), ratings.iter()
+            .take(RATINGS_SHOWN)
+            .enumerate()
+            .map(|(k, r)| format!("**{}.** {} — {:.0} ± {:.0} ({} games)", k + 1, r.username, r.rating, 2.0 * r.rd, r.games))
+            .collect::<Vec<_>>()
+            .join("\n"), false);
+    }
+    embeds::respond(ctx, i, embed).await?;
+
+    Ok(())
+}
+
+fn handle_leaderboard(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut mode = String::new();
+    let mut alltime = false;
+    let mut page = 1;
+    let mut season = None;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("mode", Some(value)) => mode.push_str(value.as_str().unwrap()),
+            ("alltime", Some(value)) => alltime = value.as_bool().unwrap_or(false),
+            ("page", Some(value)) => page = value.as_u64().unwrap_or(1).max(1) as usize,
+            ("season", Some(value)) => season = value.as_u64().map(|n| n as u32),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let mode: Mode = match mode.parse() {
+            Ok(mode) => mode,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Leaderboard Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+        let guild = match i.guild_id {
+            Some(guild) => guild,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Leaderboard Error"), "leaderboards are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        if let Some(season) = season {
+            return season_leaderboard(&ctx, &i, guild.0, season, mode, alltime, page).await;
+        }
+
+        embeds::defer(&ctx, &i).await?;
+
+        let db = DB.get().await;
+        let member_ids = crate::guild_members(&ctx.http, guild).await.map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|m| m.user.id.0)
+            .collect::<HashSet<_>>();
+        let users = db.get_users().await.map_err(|e| e.to_string())?;
+        let discord_of = users.iter().cloned().map(|(d, u)| (u, d)).collect::<HashMap<_, _>>();
+        let members = users.into_iter()
+            .filter(|(discord, _)| member_ids.contains(discord))
+            .map(|(_, username)| username)
+            .collect::<HashSet<_>>();
+
+        let times = db.get_snapshot_times(2).await.map_err(|e| e.to_string())?;
+        let current = match times.first() {
+            Some(&time) => rank(&db.get_stars_at(time).await.map_err(|e| e.to_string())?, &members, mode, alltime),
+            None => Vec::new(),
+        };
+        let previous = match times.get(1) {
+            Some(&time) => rank(&db.get_stars_at(time).await.map_err(|e| e.to_string())?, &members, mode, alltime),
+            None => Vec::new(),
+        };
+        let previous = previous.into_iter().enumerate().map(|(k, (u, _))| (u, k)).collect::<HashMap<_, _>>();
+
+        let pages = current.len().div_ceil(PAGE_SIZE).max(1);
+        let page = page.min(pages);
+        let lines = current.iter().enumerate()
+            .skip((page - 1) * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .map(|(k, (username, stars))| {
+                let change = match previous.get(username) {
+                    Some(&p) if p > k => format!("▲{}", p - k),
+                    Some(&p) if p < k => format!("▼{}", k - p),
+                    Some(_) => "–".to_string(),
+                    None => "new".to_string(),
+                };
+                let discord = discord_of.get(username).map(|d| format!(" (<@{}>)", d)).unwrap_or_default();
+                format!("**{}.** {}{} — {:.2} `{}`", k + 1, username, discord, stars, change)
+            })
+            .collect::<Vec<_>>();
+
+        let mut description = match times.first() {
+            Some(time) => format!("Updated <t:{}:R>\n\n", time),
+            None => String::new(),
+        };
+        description.push_str(&if lines.is_empty() { "*no registered members have stars yet*".to_string() } else { lines.join("\n") });
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("{} {}Leaderboard", mode, if alltime { "All-Time " } else { "" }))
+            .description(description)
+            .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_LEADERBOARD: Command = Command {
+        name: "leaderboard".into(),
+        description: "ranks registered server members by stars".into(),
+        args: vec![
+            Arg { name: "mode".into(), description: "ffa, duel or 2v2".into(), required: true, type_: TypeId::of::<String>() },
+            Arg { name: "alltime".into(), description: "rank by all-time stars".into(), required: false, type_: TypeId::of::<bool>() },
+            Arg { name: "page".into(), description: "page number".into(), required: false, type_: TypeId::of::<u32>() },
+            Arg { name: "season".into(), description: "show the final standings of a past season".into(), required: false, type_: TypeId::of::<u32>() },
+        ],
+        handler: handle_leaderboard,
+        subcommands: Vec::new(),
+    };
+}
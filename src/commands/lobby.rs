@@ -0,0 +1,217 @@
+//! `/lobby` command for scheduled custom games
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::{CreateComponents, CreateEmbed}, client::Context, http::Http, model::{id::ChannelId, interactions::{application_command::ApplicationCommandInteraction, message_component::{ButtonStyle, MessageComponentInteraction}, InteractionApplicationCommandCallbackDataFlags, InteractionResponseType}}};
+
+use crate::{embeds, lobbies::Lobby, palette, rooms::{self, Speed}, DB};
+use super::{Arg, Command};
+
+/// How long before the start participants are pinged.
+const PING_BEFORE: u64 = 10 * 60;
+
+/// Parses a start time: seconds since the epoch, a Discord timestamp
+/// (`<t:1234567890:F>`) or a delay from `now` such as `2h30m`.
+pub fn parse_time(s: &str, now: u64) -> Option<u64> {
+    let s = s.trim().trim_start_matches("in ").trim();
+    if let Ok(time) = s.parse() {
+        return Some(time);
+    }
+    if let Some(timestamp) = s.strip_prefix("<t:").and_then(|t| t.strip_suffix('>')) {
+        return timestamp.split(':').next()?.parse().ok();
+    }
+
+    let mut delay = 0;
+    let mut number = String::new();
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        delay = number.parse::<u64>().ok()?.checked_mul(unit)?.checked_add(delay)?;
+        number.clear();
+    }
+    if delay == 0 || !number.is_empty() { None } else { now.checked_add(delay) }
+}
+
+pub fn lobby_embed(lobby: &Lobby, rsvps: &[u64]) -> CreateEmbed {
+    let mut description = format!("**Start:** <t:{}:F> (<t:{}:R>)\n**Host:** <@{}>", lobby.time, lobby.time, lobby.creator);
+    if let Some(map) = &lobby.map {
+        description += &format!("\n**Map:** {}", map);
+    }
+    if let Some(speed) = lobby.speed {
+        description += &format!("\n**Speed:** {}", speed);
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title(if lobby.closed { "Scheduled Game (closed)" } else { "Scheduled Game" })
+        .description(description)
+        .field(format!("Players ({})", rsvps.len()), if rsvps.is_empty() {
+            "*nobody yet*".to_string()
+        } else {
+            rsvps.iter().map(|d| format!("<@{}>", d)).collect::<Vec<_>>().join("\n")
+        }, false)
+        .color(palette::EMBED_GAME);
+    embed
+}
+
+fn buttons(c: &mut CreateComponents) -> &mut CreateComponents {
+    c.create_action_row(|row| {
+        row.create_button(|b| b.custom_id("lobby:join").label("Join").style(ButtonStyle::Success))
+            .create_button(|b| b.custom_id("lobby:leave").label("Leave").style(ButtonStyle::Secondary))
+    })
+}
+
+fn handle_schedule(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut time = String::new();
+    let mut map = None;
+    let mut speed = None;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("time", Some(value)) => time.push_str(value.as_str().unwrap()),
+            ("map", Some(value)) => map = value.as_str().map(|s| s.to_string()),
+            ("speed", Some(value)) => speed = value.as_str().map(|s| s.to_string()),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Lobby Error"), "lobbies are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+        let now = crate::unix_time();
+        let time = match parse_time(&time, now) {
+            Some(time) if time > now => time,
+            _ => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Lobby Error"), "time must be in the future, e.g. `2h30m` or a unix timestamp")).await?;
+                return Ok(())
+            }
+        };
+        let speed = match speed.map(|s| s.parse::<Speed>()).transpose() {
+            Ok(speed) => speed,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Lobby Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+
+        let mut lobby = Lobby {
+            message: 0,
+            guild: guild.0,
+            channel: i.channel_id.0,
+            creator: i.user.id.0,
+            time,
+            code: rooms::random_code(),
+            map,
+            speed,
+            pinged: false,
+            closed: false,
+        };
+        let embed = lobby_embed(&lobby, &[]);
+        i.create_interaction_response(&ctx.http, |resp| {
+            resp.interaction_response_data(|data| data.add_embed(embed).components(buttons))
+        }).await?;
+
+        lobby.message = i.get_interaction_response(&ctx.http).await?.id.0;
+        DB.get().await.add_lobby(&lobby).await?;
+
+        Ok(())
+    })
+}
+
+/// Handles the Join and Leave buttons on lobby messages.
+pub fn on_button(ctx: &Context, i: &MessageComponentInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let db = DB.get().await;
+        let message = i.message.id().0;
+        let lobby = db.get_lobby(message).await?;
+        let lobby = match lobby {
+            Some(lobby) if !lobby.closed => lobby,
+            _ => {
+                i.create_interaction_response(&ctx.http, |resp| {
+                    resp.interaction_response_data(|data| {
+                        data.content("This lobby is closed").flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                    })
+                }).await?;
+                return Ok(())
+            }
+        };
+
+        match i.data.custom_id.as_str() {
+            "lobby:join" => { db.add_rsvp(message, i.user.id.0).await?; },
+            "lobby:leave" => { db.remove_rsvp(message, i.user.id.0).await?; },
+            _ => {}
+        }
+
+        let embed = lobby_embed(&lobby, &db.get_rsvps(message).await?);
+        i.create_interaction_response(&ctx.http, |resp| {
+            resp.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|data| data.add_embed(embed))
+        }).await?;
+
+        Ok(())
+    })
+}
+
+/// Pings participants of lobbies about to start and closes lobbies that have started.
+pub async fn update_lobbies(http: &Http) -> crate::Result<()> {
+    let db = DB.get().await;
+    let now = crate::unix_time();
+    let lobbies = db.get_open_lobbies().await?;
+    for mut lobby in lobbies {
+        let channel = ChannelId(lobby.channel);
+        if !lobby.pinged && now + PING_BEFORE >= lobby.time {
+            let rsvps = db.get_rsvps(lobby.message).await?;
+            let mentions = rsvps.iter().map(|d| format!("<@{}>", d)).collect::<Vec<_>>().join(" ");
+            let content = format!("{}\nThe scheduled game starts <t:{}:R>: {}", mentions, lobby.time, lobby.url());
+            let sent = channel.send_message(http, |m| m.content(content)).await;
+            if let Err(e) = sent {
+                eprintln!("lobby {}: {}", lobby.message, e);
+            }
+            db.set_lobby_pinged(lobby.message).await?;
+        }
+
+        if now >= lobby.time {
+            db.close_lobby(lobby.message).await?;
+            lobby.closed = true;
+            let embed = lobby_embed(&lobby, &db.get_rsvps(lobby.message).await?);
+            let edited = channel.edit_message(http, lobby.message, |m| m.set_embed(embed).components(|c| c)).await;
+            if let Err(e) = edited {
+                eprintln!("lobby {}: {}", lobby.message, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+lazy_static! {
+    pub static ref COMMAND_LOBBY: Command = Command::group("lobby", "scheduled custom games", vec![
+        Command {
+            name: "schedule".into(),
+            description: "schedules a custom game that players can join".into(),
+            args: vec![
+                Arg { name: "time".into(), description: "start time, e.g. 2h30m or a unix timestamp".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "map".into(), description: "custom map".into(), required: false, type_: TypeId::of::<String>() },
+                Arg { name: "speed".into(), description: "game speed, e.g. 2x".into(), required: false, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_schedule,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
@@ -0,0 +1,261 @@
+//! Command framework
+
+pub mod admin;
+pub mod announce;
+pub mod balance;
+pub mod custom;
+pub mod graph;
+pub mod h2h;
+pub mod leaderboard;
+pub mod lobby;
+pub mod queue;
+pub mod rating;
+pub mod register;
+pub mod replay;
+pub mod roles;
+pub mod season;
+pub mod tournament;
+pub mod unfurl;
+
+use core::future::Future;
+use std::{any::{self, TypeId}, sync::RwLock, collections::HashMap, borrow::Cow, pin::Pin};
+
+use serenity::{client::Context, model::{channel::Message, prelude::Ready, id::CommandId, interactions::{application_command::{ApplicationCommand, ApplicationCommandOptionType, ApplicationCommandInteraction}, message_component::MessageComponentInteraction, Interaction}}};
+
+pub type Handler = fn(&Context, &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>>;
+pub type ErrorHandler = fn(&Context, &ApplicationCommandInteraction, Box<dyn std::error::Error>) -> Pin<Box<dyn Future<Output=()> + Send>>;
+pub type MessageHandler = fn(&Context, &Message) -> Pin<Box<dyn Future<Output=()> + Send>>;
+pub type ComponentHandler = fn(&Context, &MessageComponentInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>>;
+
+#[derive(Clone)]
+pub struct Arg {
+    pub name: Cow<'static, str>,
+    pub description: Cow<'static, str>,
+    pub type_: any::TypeId,
+    pub required: bool
+}
+
+#[derive(Clone)]
+pub struct Command {
+    pub name: Cow<'static, str>,
+    pub description: Cow<'static, str>,
+    pub args: Vec<Arg>,
+    pub handler: Handler,
+    /// If non-empty, `args` and `handler` are ignored and the
+    /// subcommand named by the first option is run instead.
+    pub subcommands: Vec<Command>,
+}
+
+impl Command {
+    /// Creates a command that only dispatches to its subcommands.
+    pub fn group(name: impl Into<Cow<'static, str>>, description: impl Into<Cow<'static, str>>, subcommands: Vec<Command>) -> Command {
+        Command {
+            name: name.into(),
+            description: description.into(),
+            args: Vec::new(),
+            handler: |_, _| Box::pin(async { Ok(()) }),
+            subcommands,
+        }
+    }
+
+    /// Finds the handler to run for `i`. Subcommand interactions are
+    /// rewritten so that the subcommand's options are at the top level.
+    fn resolve(&self, i: &ApplicationCommandInteraction) -> Option<(Handler, ApplicationCommandInteraction)> {
+        if self.subcommands.is_empty() {
+            return Some((self.handler, i.clone()));
+        }
+
+        let opt = i.data.options.iter().find(|opt| opt.kind == ApplicationCommandOptionType::SubCommand)?;
+        let subcommand = self.subcommands.iter().find(|cmd| cmd.name == opt.name)?;
+        let mut i = i.clone();
+        i.data.options = opt.options.clone();
+        Some((subcommand.handler, i))
+    }
+}
+
+pub struct Commands {
+    // Used before ready is called
+    // DOES NOT CHANGE. EVER.
+    commands: Vec<Command>,
+
+    // Filled after ready is called
+    commands_map: RwLock<HashMap<CommandId, usize>>,
+
+    on_error: Option<ErrorHandler>,
+
+    on_message: Option<MessageHandler>,
+
+    /// Handlers for buttons, by custom ID prefix.
+    components: Vec<(&'static str, ComponentHandler)>,
+}
+
+/// Checks that `i` was run in a server by an admin, responding with an error otherwise.
+pub(crate) async fn admin_guild(ctx: &Context, i: &ApplicationCommandInteraction, title: &'static str) -> crate::Result<Option<u64>> {
+    match i.guild_id {
+        Some(guild) if crate::is_admin(i) => Ok(Some(guild.0)),
+        Some(_) => {
+            crate::embeds::respond(ctx, i, crate::embeds::error(Some(title), "only server managers can do this")).await?;
+            Ok(None)
+        },
+        None => {
+            crate::embeds::respond(ctx, i, crate::embeds::error(Some(title), "this is only available in servers")).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Checks that `i` was run in a server by an owner of the bot, responding
+/// with an error otherwise. Registrations are shared by every server, so
+/// overriding them can't be left to each server's managers.
+pub(crate) async fn owner_guild(ctx: &Context, i: &ApplicationCommandInteraction, title: &'static str) -> crate::Result<Option<u64>> {
+    let guild = match i.guild_id {
+        Some(guild) => guild.0,
+        None => {
+            crate::embeds::respond(ctx, i, crate::embeds::error(Some(title), "this is only available in servers")).await?;
+            return Ok(None)
+        }
+    };
+    let info = ctx.http.get_current_application_info().await?;
+    let owner = info.owner.id == i.user.id || info.team.is_some_and(|t| t.members.iter().any(|m| m.user.id == i.user.id));
+    if !owner {
+        crate::embeds::respond(ctx, i, crate::embeds::error(Some(title), "only the bot's owners can do this")).await?;
+        return Ok(None)
+    }
+    Ok(Some(guild))
+}
+
+fn typeid_to_optiontype(typeid: TypeId) -> ApplicationCommandOptionType {
+    if typeid == TypeId::of::<u64>() ||
+        typeid == TypeId::of::<u32>() ||
+        typeid == TypeId::of::<u16>() ||
+        typeid == TypeId::of::<u8>() ||
+        typeid == TypeId::of::<u128>() ||
+        typeid == TypeId::of::<i64>() ||
+        typeid == TypeId::of::<i32>() ||
+        typeid == TypeId::of::<i16>() ||
+        typeid == TypeId::of::<i8>() ||
+        typeid == TypeId::of::<i128>() ||
+
+        typeid == TypeId::of::<usize>() ||
+        typeid == TypeId::of::<isize>() {
+            ApplicationCommandOptionType::Integer
+    } else if typeid == TypeId::of::<f32>() ||
+        typeid == TypeId::of::<f64>()  {
+            ApplicationCommandOptionType::Number
+    } else if typeid == TypeId::of::<bool>() {
+            ApplicationCommandOptionType::Boolean
+    } else if typeid == TypeId::of::<serenity::model::user::User>() || typeid == TypeId::of::<serenity::model::guild::Member>() {
+            ApplicationCommandOptionType::User
+    } else if typeid == TypeId::of::<serenity::model::guild::Role>() {
+            ApplicationCommandOptionType::Role
+    } else {
+            ApplicationCommandOptionType::String
+    }
+}
+
+impl Commands {
+    pub fn new(commands: Vec<Command>) -> Commands {
+        Commands {
+            commands,
+            commands_map: Default::default(),
+            on_error: None,
+            on_message: None,
+            components: Vec::new(),
+        }
+    }
+
+    pub fn on_error(&mut self, f: ErrorHandler) {
+        self.on_error = Some(f);
+    }
+
+    /// Sets a handler run for every message the bot can see.
+    pub fn on_message(&mut self, f: MessageHandler) {
+        self.on_message = Some(f);
+    }
+
+    /// Sets the handler for components whose custom ID starts with `prefix`.
+    pub fn on_component(&mut self, prefix: &'static str, f: ComponentHandler) {
+        self.components.push((prefix, f));
+    }
+}
+
+#[serenity::async_trait]
+impl serenity::client::EventHandler for Commands {
+    async fn ready(&self, ctx: Context, _ready: Ready) {
+        let mut futures_ = Vec::new();
+        for command in self.commands.iter() {
+            let name = command.name.clone();
+            let desc = command.description.clone();
+            let args = command.args.clone();
+            let subcommands = command.subcommands.clone();
+            futures_.push(ApplicationCommand::create_global_application_command(&ctx.http, |cmd| {
+                cmd.name(name).description(desc);
+                for arg in args {
+                    cmd.create_option(|opt| {
+                        opt.name(&arg.name)
+                            .description(&arg.description)
+                            .required(arg.required)
+                            .kind(typeid_to_optiontype(arg.type_))
+                    });
+                }
+                for subcommand in subcommands {
+                    cmd.create_option(|opt| {
+                        opt.name(&subcommand.name)
+                            .description(&subcommand.description)
+                            .kind(ApplicationCommandOptionType::SubCommand);
+                        for arg in &subcommand.args {
+                            opt.create_sub_option(|sub| {
+                                sub.name(&arg.name)
+                                    .description(&arg.description)
+                                    .required(arg.required)
+                                    .kind(typeid_to_optiontype(arg.type_))
+                            });
+                        }
+                        opt
+                    });
+                }
+                cmd
+            }));
+        }
+
+        let out = futures::future::join_all(futures_).await.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>();
+        let mut map = self.commands_map.write().unwrap();
+        for (idx, item) in out.iter().enumerate() {
+            map.insert(item.id, idx);
+            eprintln!("registered command: {} {}", item.name, item.id);
+        }
+    }
+
+
+    async fn message(&self, ctx: Context, msg: Message) {
+        if let Some(f) = self.on_message {
+            f(&ctx, &msg).await;
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::ApplicationCommand(cmd) = interaction {
+            let handler = {
+                let map = self.commands_map.read().unwrap();
+                self.commands.get(*map.get(&cmd.data.id).unwrap_or(&10000))
+            };
+            if let Some((handler, cmd)) = handler.and_then(|handler| handler.resolve(&cmd)) {
+                let future = match handler(&ctx, &cmd).await {
+                    Err(e) => self.on_error.map(|f| f(&ctx, &cmd, e)),
+                    _ => None
+                };
+
+                if let Some(future) = future {
+                    future.await;
+                }
+            }
+        } else if let Interaction::MessageComponent(component) = interaction {
+            let handler = self.components.iter().find(|(prefix, _)| component.data.custom_id.starts_with(prefix));
+            if let Some((_, handler)) = handler {
+                if let Err(e) = handler(&ctx, &component).await {
+                    eprintln!("component {}: {}", component.data.custom_id, e);
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
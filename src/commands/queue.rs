@@ -0,0 +1,327 @@
+//! `/queue` matchmaking for inhouse games
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::{CreateComponents, CreateEmbed}, client::Context, http::Http, model::{id::ChannelId, interactions::{application_command::ApplicationCommandInteraction, message_component::{ButtonStyle, MessageComponentInteraction}, InteractionApplicationCommandCallbackDataFlags, InteractionResponseType}}};
+
+use crate::{balance, embeds, matchmaking::{QueueEntry, QueueMatch, QueuePlayer}, palette, rooms, stars::{self, Mode}, DB};
+use super::{Arg, Command};
+
+/// Stars assumed for players without stars in the queued mode.
+const DEFAULT_STARS: f64 = 40.0;
+const FFA_PLAYERS: usize = 4;
+/// How long a player waits in the queue before being removed.
+pub const QUEUE_TIMEOUT: u64 = 30 * 60;
+/// How long popped players have to ready up.
+pub const READY_TIMEOUT: u64 = 2 * 60;
+
+fn players_needed(mode: Mode) -> usize {
+    match mode {
+        Mode::Duel => 2,
+        Mode::M2v2 => 4,
+        Mode::Ffa => FFA_PLAYERS,
+    }
+}
+
+/// The `count` queued players with the closest stars, if there are enough.
+fn closest(queue: &[QueueEntry], count: usize) -> Option<Vec<&QueueEntry>> {
+    let mut sorted = queue.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.stars.partial_cmp(&b.stars).unwrap_or(std::cmp::Ordering::Equal));
+    sorted.windows(count)
+        .min_by(|a, b| {
+            let spread = |w: &[&QueueEntry]| w[w.len() - 1].stars - w[0].stars;
+            spread(a).partial_cmp(&spread(b)).unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|w| w.to_vec())
+}
+
+fn ready_button(c: &mut CreateComponents) -> &mut CreateComponents {
+    c.create_action_row(|row| row.create_button(|b| b.custom_id("queue:ready").label("Ready").style(ButtonStyle::Success)))
+}
+
+fn match_embed(m: &QueueMatch, players: &[QueuePlayer]) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("{} Match Found", m.mode))
+        .description(format!("Press **Ready** <t:{}:R> to get the room link.", m.created + READY_TIMEOUT))
+        .field("Players", players.iter()
+            .map(|p| format!("{} <@{}> ({:.0}★)", if p.ready { "✅" } else { "⌛" }, p.discord, p.stars))
+            .collect::<Vec<_>>()
+            .join("\n"), false)
+        .color(palette::EMBED_GAME);
+    embed
+}
+
+/// Pops a match for `mode` in `guild` if enough players are queued.
+async fn try_pop(http: &Http, guild: u64, mode: Mode) -> crate::Result<()> {
+    // one pop at a time, so concurrent joins don't post the same players twice
+    let _lock = POP_LOCK.lock().await;
+    let db = DB.get().await;
+    let queue = db.get_queue(guild, mode).await?;
+    let popped = match closest(&queue, players_needed(mode)) {
+        Some(popped) => popped,
+        None => return Ok(()),
+    };
+
+    let mut m = QueueMatch {
+        message: 0,
+        guild,
+        channel: popped[0].channel,
+        mode,
+        code: rooms::random_code(),
+        created: crate::unix_time(),
+    };
+    let players = popped.iter()
+        .map(|e| QueuePlayer { discord: e.discord, stars: e.stars, joined: e.joined, ready: false })
+        .collect::<Vec<_>>();
+    let mentions = players.iter().map(|p| format!("<@{}>", p.discord)).collect::<Vec<_>>().join(" ");
+    let embed = match_embed(&m, &players);
+    let message = ChannelId(m.channel).send_message(http, |msg| msg.content(mentions).set_embed(embed).components(ready_button)).await?;
+
+    // players stay queued until the match is posted, and someone leaving
+    // in the meantime calls it off
+    let discords = players.iter().map(|p| p.discord).collect::<Vec<_>>();
+    if !db.claim_queue_entries(guild, mode, &discords).await? {
+        message.delete(http).await?;
+        return Ok(());
+    }
+    m.message = message.id.0;
+    db.add_queue_match(&m, &players).await?;
+    eprintln!("queue: popped {} match {} in {}", mode, m.message, guild);
+    Ok(())
+}
+
+fn handle_join(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut mode = String::new();
+    for opt in &i.data.options {
+        if let ("mode", Some(value)) = (opt.name.as_str(), &opt.value) {
+            mode.push_str(value.as_str().unwrap());
+        }
+    }
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild.0,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Queue Error"), "the queue is only available in servers")).await?;
+                return Ok(())
+            }
+        };
+        let mode: Mode = match mode.parse() {
+            Ok(mode) => mode,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Queue Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+        let db = DB.get().await;
+        let username = db.get_username(i.user.id.0).await?;
+        let username = match username {
+            Some(username) => username,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Queue Error"), "link your generals.io account with `/register` first")).await?;
+                return Ok(())
+            }
+        };
+        if db.in_queue_match(i.user.id.0).await? {
+            embeds::respond(&ctx, &i, embeds::error(Some("Queue Error"), "you're already in a match waiting for its ready check")).await?;
+            return Ok(())
+        }
+
+        embeds::defer(&ctx, &i).await?;
+        let stars = stars::fetch(&username).await.map_err(|e| e.to_string())?.get(mode, false).unwrap_or(DEFAULT_STARS);
+        let entry = QueueEntry { guild, channel: i.channel_id.0, discord: i.user.id.0, mode, stars, joined: crate::unix_time() };
+        db.add_queue_entry(&entry).await.map_err(|e| e.to_string())?;
+        let waiting = db.get_queue(guild, mode).await.map_err(|e| e.to_string())?.len();
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Joined {} Queue", mode))
+            .description(format!("{} of {} players waiting. You'll be removed <t:{}:R> if no match is found.",
+                waiting, players_needed(mode), entry.joined + QUEUE_TIMEOUT))
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        try_pop(&ctx.http, guild, mode).await.map_err(|e| e.to_string())?;
+
+        Ok(())
+    })
+}
+
+fn handle_leave(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let left = match i.guild_id {
+            Some(guild) => DB.get().await.remove_queue_entry(guild.0, i.user.id.0).await?,
+            None => false,
+        };
+        if !left {
+            embeds::respond(&ctx, &i, embeds::error(Some("Queue Error"), "you aren't queued")).await?;
+            return Ok(())
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Left Queue").color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_status(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild.0,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Queue Error"), "the queue is only available in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        let db = DB.get().await;
+        let mut embed = CreateEmbed::default();
+        embed.title("Queue").color(palette::EMBED_GAME);
+        for mode in [Mode::Duel, Mode::M2v2, Mode::Ffa].iter().copied() {
+            let queue = db.get_queue(guild, mode).await?;
+            embed.field(format!("{} ({}/{})", mode, queue.len(), players_needed(mode)), if queue.is_empty() {
+                "*empty*".to_string()
+            } else {
+                queue.iter().map(|e| format!("<@{}> <t:{}:R>", e.discord, e.joined)).collect::<Vec<_>>().join("\n")
+            }, true);
+        }
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+/// Handles the Ready button, posting the room once everyone is ready.
+pub fn on_button(ctx: &Context, i: &MessageComponentInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let db = DB.get().await;
+        let message = i.message.id().0;
+        let m = db.get_queue_match(message).await?;
+        let ready = match &m {
+            Some(_) => db.set_queue_ready(message, i.user.id.0).await?,
+            None => false,
+        };
+        let m = match (m, ready) {
+            (Some(m), true) => m,
+            (m, _) => {
+                let content = if m.is_some() { "You aren't in this match" } else { "This match is no longer active" };
+                i.create_interaction_response(&ctx.http, |resp| {
+                    resp.interaction_response_data(|data| data.content(content).flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL))
+                }).await?;
+                return Ok(())
+            }
+        };
+
+        let players = db.get_queue_players(message).await?;
+        let mut embed = match_embed(&m, &players);
+        let all_ready = players.iter().all(|p| p.ready);
+        if all_ready {
+            db.remove_queue_match(message).await?;
+            let url = rooms::room_url(&m.code, None, None);
+            embed.title(format!("{} Match Ready", m.mode)).url(&url).description(format!("**Room:** {}", url));
+            if m.mode == Mode::M2v2 {
+                let stars = players.iter().map(|p| p.stars).collect::<Vec<_>>();
+                let teams = balance::balance(&stars, 2);
+                for team in 0..2 {
+                    embed.field(format!("Team {}", team + 1), players.iter().zip(&teams)
+                        .filter(|(_, &t)| t == team)
+                        .map(|(p, _)| format!("<@{}>", p.discord))
+                        .collect::<Vec<_>>()
+                        .join("\n"), true);
+                }
+            }
+        }
+
+        i.create_interaction_response(&ctx.http, |resp| {
+            resp.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|data| {
+                    data.add_embed(embed);
+                    if all_ready {
+                        data.components(|c| c);
+                    }
+                    data
+                })
+        }).await?;
+
+        Ok(())
+    })
+}
+
+/// Drops players who waited too long, and cancels ready checks that timed
+/// out, putting the players who did ready back in the queue.
+pub async fn expire(http: &Http) -> crate::Result<()> {
+    let db = DB.get().await;
+    let now = crate::unix_time();
+
+    let stale = db.remove_stale_queue_entries(now.saturating_sub(QUEUE_TIMEOUT)).await?;
+    for entry in stale {
+        let content = format!("<@{}> no {} match was found, so you left the queue", entry.discord, entry.mode);
+        if let Err(e) = ChannelId(entry.channel).send_message(http, |m| m.content(content)).await {
+            eprintln!("queue: {}", e);
+        }
+    }
+
+    let matches = db.get_queue_matches().await?;
+    for m in matches.into_iter().filter(|m| now >= m.created + READY_TIMEOUT) {
+        let players = db.get_queue_players(m.message).await?;
+        db.remove_queue_match(m.message).await?;
+        // ready players start waiting afresh, so they aren't dropped as stale right away
+        for p in players.iter().filter(|p| p.ready) {
+            let entry = QueueEntry { guild: m.guild, channel: m.channel, discord: p.discord, mode: m.mode, stars: p.stars, joined: now };
+            db.add_queue_entry(&entry).await?;
+        }
+
+        let missing = players.iter().filter(|p| !p.ready).map(|p| format!("<@{}>", p.discord)).collect::<Vec<_>>().join(" ");
+        let mut embed = match_embed(&m, &players);
+        embed.title(format!("{} Match Cancelled", m.mode))
+            .description(format!("Not everyone was ready: {}. Ready players are back in the queue.", missing));
+        let edited = ChannelId(m.channel).edit_message(http, m.message, |msg| msg.set_embed(embed).components(|c| c)).await;
+        if let Err(e) = edited {
+            eprintln!("queue: {}", e);
+        }
+        if let Err(e) = try_pop(http, m.guild, m.mode).await.map_err(|e| e.to_string()) {
+            eprintln!("queue: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+lazy_static! {
+    static ref POP_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+
+    pub static ref COMMAND_QUEUE: Command = Command::group("queue", "matchmaking for inhouse games", vec![
+        Command {
+            name: "join".into(),
+            description: "waits for a match against players with similar stars".into(),
+            args: vec![
+                Arg { name: "mode".into(), description: "1v1, 2v2 or ffa".into(), required: true, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_join,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "leave".into(),
+            description: "leaves the queue".into(),
+            args: Vec::new(),
+            handler: handle_leave,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "status".into(),
+            description: "shows who is queued".into(),
+            args: Vec::new(),
+            handler: handle_status,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
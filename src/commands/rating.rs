@@ -0,0 +1,266 @@
+//! `/rating` commands for server-local ratings
+
+use std::{any::TypeId, collections::HashSet, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::{id::GuildId, interactions::application_command::ApplicationCommandInteraction}};
+
+use crate::{chart, embeds, palette, ratings::{self, Pool, RatedGame}, replay::{self, metadata}, DB};
+use super::{Arg, Command};
+
+/// Replays of a player searched for a submitted game.
+const SUBMIT_SEARCH: usize = 200;
+const PAGE_SIZE: usize = 100;
+const RECENT_GAMES: usize = 10;
+
+/// Parses the `user` and `pool` options of `i`.
+fn user_and_pool(i: &ApplicationCommandInteraction) -> (Option<String>, String) {
+    let mut user = None;
+    let mut pool = "1v1".to_string();
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("user", Some(value)) => user = value.as_str().map(|s| s.to_string()),
+            ("pool", Some(value)) => pool = value.as_str().unwrap().to_string(),
+            _ => {}
+        }
+    }
+    (user, pool)
+}
+
+/// Resolves the player and pool a rating command is about, responding with an
+/// error if either is invalid. Without a user, the caller's account is used.
+async fn lookup(ctx: &Context, i: &ApplicationCommandInteraction) -> crate::Result<Option<(u64, String, Pool)>> {
+    let (user, pool) = user_and_pool(i);
+    let guild = match i.guild_id {
+        Some(guild) => guild.0,
+        None => {
+            embeds::respond(ctx, i, embeds::error(Some("Rating Error"), "ratings are only available in servers")).await?;
+            return Ok(None)
+        }
+    };
+    let pool = match pool.parse::<Pool>() {
+        Ok(pool) => pool,
+        Err(e) => {
+            embeds::respond(ctx, i, embeds::error(Some("Rating Error"), format!("{}", e))).await?;
+            return Ok(None)
+        }
+    };
+    let username = match user {
+        Some(user) => crate::resolve_username(&user).await?,
+        None => DB.get().await.get_username(i.user.id.0).await?,
+    };
+    match username {
+        Some(username) => Ok(Some((guild, username, pool))),
+        None => {
+            embeds::respond(ctx, i, embeds::error(Option::<String>::None, "Discord user not registered")).await?;
+            Ok(None)
+        }
+    }
+}
+
+fn handle_show(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let (guild, username, pool) = match lookup(&ctx, &i).await? {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        let (_, histories) = ratings::histories(guild, pool).await?;
+        let history = match histories.get(&username.to_lowercase()) {
+            Some(history) => history,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Rating Error"), format!("{} has no rated {} games here", username, pool))).await?;
+                return Ok(())
+            }
+        };
+        let rating = history.last().unwrap().1;
+        let rank = 1 + histories.values().filter(|h| h.last().unwrap().1.rating > rating.rating).count();
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("{} {} Rating", username, pool))
+            .description(format!("**Rating**: {:.0} ± {:.0}\n**Rank**: #{} of {}\n**Games**: {}",
+                rating.rating, 2.0 * rating.rd, rank, histories.len(), history.len()))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_history(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let (guild, username, pool) = match lookup(&ctx, &i).await? {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        embeds::defer(&ctx, &i).await?;
+        let (games, histories) = ratings::histories(guild, pool).await.map_err(|e| e.to_string())?;
+        let history = match histories.get(&username.to_lowercase()) {
+            Some(history) => history,
+            None => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Rating Error"), format!("{} has no rated {} games here", username, pool)), None).await?;
+                return Ok(())
+            }
+        };
+
+        let played = games.iter().filter(|g| g.ranking.iter().any(|u| u.eq_ignore_ascii_case(&username))).collect::<Vec<_>>();
+        let mut previous = ratings::History::new();
+        previous.push((0, Default::default()));
+        previous.extend(history.iter().copied());
+        let recent = played.iter().zip(previous.windows(2)).rev()
+            .take(RECENT_GAMES)
+            .map(|(g, w)| {
+                let place = g.ranking.iter().position(|u| u.eq_ignore_ascii_case(&username)).unwrap_or(0) + 1;
+                format!("[#{}](https://generals.io/replays/{}) <t:{}:R>: {:.0} ({:+.0})", place, g.id, g.started, w[1].1.rating, w[1].1.rating - w[0].1.rating)
+            })
+            .collect::<Vec<_>>();
+
+        let points = history.iter().map(|&(t, r)| (t, r.rating)).collect::<Vec<_>>();
+        let png = chart::line_chart(&[(palette::PLAYERS[0], points)]).to_png()?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("{} {} Rating History", username, pool))
+            .field("Recent Games", recent.join("\n"), false)
+            .image("attachment://rating.png")
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, Some((png, "rating.png"))).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_submit(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut id = String::new();
+    for opt in &i.data.options {
+        if let ("replay", Some(value)) = (opt.name.as_str(), &opt.value) {
+            let value = value.as_str().unwrap();
+            id.push_str(value.rsplit('/').next().unwrap_or(value));
+        }
+    }
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild.0,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Rating Error"), "ratings are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        embeds::defer(&ctx, &i).await?;
+        let replay = match replay::fetch(&id).await.map_err(|e| e.to_string()) {
+            Ok(replay) => replay,
+            Err(e) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Rating Error"), e), None).await?;
+                return Ok(())
+            }
+        };
+
+        let player = match replay.usernames.first() {
+            Some(player) => player,
+            None => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Rating Error"), "that replay has no players"), None).await?;
+                return Ok(())
+            }
+        };
+
+        // the replay itself doesn't say whether it was a custom game, but its
+        // listing in a player's history does
+        let mut game = None;
+        let mut offset = 0;
+        while game.is_none() && offset < SUBMIT_SEARCH {
+            let page = metadata::fetch(player, offset, PAGE_SIZE).await.map_err(|e| e.to_string())?;
+            let len = page.len();
+            game = page.into_iter().find(|g| g.id == replay.id);
+            if len < PAGE_SIZE {
+                break;
+            }
+            offset += len;
+        }
+        let db = DB.get().await;
+        let member_ids = crate::guild_members(&ctx.http, GuildId(guild)).await.map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|m| m.user.id.0)
+            .collect::<HashSet<_>>();
+        let users = db.get_users().await.map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|(discord, _)| member_ids.contains(discord))
+            .collect::<Vec<_>>();
+        let rated = match &game {
+            None => Err("couldn't find the game in its players' recent history"),
+            Some(g) if g.type_ != "custom" => Err("only custom games are rated"),
+            Some(g) => RatedGame::new(guild, g, Some(&replay), &users).ok_or("every player must be registered and in this server"),
+        };
+        let rated = match rated {
+            Ok(rated) if ratings::record(&rated).await.map_err(|e| e.to_string())? => rated,
+            Ok(_) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Rating Error"), "that game was already rated"), None).await?;
+                return Ok(())
+            },
+            Err(e) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Rating Error"), e), None).await?;
+                return Ok(())
+            }
+        };
+
+        let (_, histories) = ratings::histories(guild, rated.pool).await.map_err(|e| e.to_string())?;
+        let lines = rated.ranking.iter().enumerate()
+            .map(|(k, u)| {
+                let h = &histories[&u.to_lowercase()];
+                let after = h[h.len() - 1].1.rating;
+                let before = if h.len() > 1 { h[h.len() - 2].1.rating } else { 1500.0 };
+                format!("**{}.** {} — {:.0} ({:+.0})", k + 1, u, after, after - before)
+            })
+            .collect::<Vec<_>>();
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Rated {} Game", rated.pool))
+            .url(format!("https://generals.io/replays/{}", rated.id))
+            .description(lines.join("\n"))
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        Ok(())
+    })
+}
+
+fn user_args() -> Vec<Arg> {
+    vec![
+        Arg { name: "user".into(), description: "generals.io username or discord mention (defaults to you)".into(), required: false, type_: TypeId::of::<String>() },
+        Arg { name: "pool".into(), description: "1v1, ffa or teams (defaults to 1v1)".into(), required: false, type_: TypeId::of::<String>() },
+    ]
+}
+
+lazy_static! {
+    pub static ref COMMAND_RATING: Command = Command::group("rating", "server ratings from custom games", vec![
+        Command {
+            name: "show".into(),
+            description: "shows a player's server rating".into(),
+            args: user_args(),
+            handler: handle_show,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "history".into(),
+            description: "charts a player's server rating".into(),
+            args: user_args(),
+            handler: handle_history,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "submit".into(),
+            description: "rates a custom game between registered members".into(),
+            args: vec![
+                Arg { name: "replay".into(), description: "replay link or ID".into(), required: true, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_submit,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
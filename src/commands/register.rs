@@ -0,0 +1,224 @@
+//! `/register` command, with proof of account ownership
+//!
+//! Registering issues a custom room code. The user plays a game in that room
+//! and types the code in its chat; finding that message in one of the
+//! account's replays proves they control it. Replay listings don't include
+//! room codes, so the chat message is what ties the game to the challenge.
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::{CreateComponents, CreateEmbed}, client::Context, model::{guild::Member, id::GuildId, interactions::{application_command::ApplicationCommandInteraction, message_component::{ButtonStyle, MessageComponentInteraction}, InteractionApplicationCommandCallbackDataFlags, InteractionResponseType}}};
+
+use crate::{embeds, palette, registration::{self, Verification}, roles, rooms, DB};
+use super::{Arg, Command};
+
+/// How long a registration challenge stays valid.
+const VERIFY_LIFETIME: u64 = 60 * 60;
+/// How long users wait between changing their own registration.
+pub const RELINK_COOLDOWN: u64 = 7 * 24 * 60 * 60;
+fn verify_button(c: &mut CreateComponents) -> &mut CreateComponents {
+    c.create_action_row(|row| row.create_button(|b| b.custom_id("register:verify").label("Verify").style(ButtonStyle::Success)))
+}
+
+/// Whether `username` is a generals.io account.
+pub(crate) async fn username_exists(username: &str) -> crate::Result<bool> {
+    let resp = reqwest::Client::new().get(format!("https://generals.io/api/validateUsername?u={}", urlencoding::encode(username)))
+        .send()
+        .await?;
+    let resp = resp.json::<serde_json::Value>().await?;
+    Ok(resp.as_bool() == Some(true))
+}
+
+fn handle_register(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut user = String::new();
+    for opt in &i.data.options {
+        if opt.name == "username" {
+            user.push_str(opt.value.as_ref().unwrap_or(&serde_json::Value::Null).as_str().unwrap());
+        }
+    }
+    let discord = i.user.id;
+
+    Box::pin(async move {
+        // check 1: username
+        if !user.to_lowercase().starts_with("[b-tier]") {
+            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), "generals.io username does not begin with [B-tier]")).await?;
+            return Ok(())
+        }
+
+        // check 2: validate username
+        if !username_exists(&user).await? {
+            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), "generals.io username does not exist")).await?;
+            return Ok(())
+        }
+
+        // check 3: check replays
+        let resp = reqwest::Client::new().get(format!("https://generals.io/api/replaysForUsername?u={}&offset=0&count=1", urlencoding::encode(&user)))
+            .send()
+            .await?;
+        let resp = resp.json::<serde_json::Value>().await?;
+        if resp.as_array().map(|x| x.len()) != Some(1) {
+            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), "generals.io username does not have games")).await?;
+            return Ok(())
+        }
+
+        // check 4: make sure the username is free; a registered discord user is relinking
+        let db = DB.get().await;
+        let has_username = db.get_discord(&user).await?.is_some();
+
+        if has_username {
+                embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), 
+                    "generals.io username already registered")).await?;
+                    return Ok(())
+        }
+
+        // check 5: relinking cooldown
+        let cooldown = db.get_link_change(discord.0).await?
+            .map(|time| time + RELINK_COOLDOWN)
+            .filter(|&until| until > crate::unix_time());
+        if let Some(until) = cooldown {
+            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), format!("you changed your registration recently; try again <t:{}:R>", until))).await?;
+            return Ok(())
+        }
+
+        // registration finishes once ownership is proven
+        let v = Verification { discord: discord.0, username: user, code: rooms::random_code(), issued: crate::unix_time() };
+        db.set_verification(&v).await?;
+
+        let url = rooms::room_url(&v.code, None, None);
+        let mut embed = CreateEmbed::default();
+        embed.title("Verify Your Account")
+            .url(&url)
+            .description(format!("To prove you own **{}**:\n1. Open {} while logged in as {}\n2. Start a game there (bring a friend or a second tab) and type `{}` in the game chat\n3. Finish or surrender, then press **Verify**\n\nThis expires <t:{}:R>.",
+                v.username, url, v.username, v.code, v.issued + VERIFY_LIFETIME))
+            .color(palette::EMBED_GAME);
+        i.create_interaction_response(&ctx.http, |resp| {
+            resp.interaction_response_data(|data| data.add_embed(embed).components(verify_button))
+        }).await?;
+
+        Ok(())
+    })
+}
+
+/// Handles the Verify button, registering the account once the challenge
+/// game is found.
+pub fn on_button(ctx: &Context, i: &MessageComponentInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let db = DB.get().await;
+        let v = db.get_verification(i.user.id.0).await?;
+        let v = match v {
+            Some(v) if crate::unix_time() < v.issued + VERIFY_LIFETIME => v,
+            _ => {
+                i.create_interaction_response(&ctx.http, |resp| {
+                    resp.interaction_response_data(|data| {
+                        data.content("You have no pending registration; run `/register` again").flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                    })
+                }).await?;
+                return Ok(())
+            }
+        };
+
+        // searching replays can take longer than an interaction may wait
+        i.create_interaction_response(&ctx.http, |resp| resp.kind(InteractionResponseType::DeferredUpdateMessage)).await?;
+
+        let previous = db.get_username(v.discord).await.map_err(|e| e.to_string())?;
+        let error = match registration::find_proof(&v).await.map_err(|e| e.to_string()) {
+            Ok(true) => {
+                // the unique index settles two users racing for one username
+                let linked = match &previous {
+                    Some(_) => db.set_username(v.discord, &v.username).await.map_err(|e| e.to_string())?,
+                    None => db.add_username(v.discord, &v.username).await.map_err(|e| e.to_string())?,
+                };
+                if linked { None } else { Some("generals.io username already registered".to_string()) }
+            },
+            Ok(false) => Some(format!("couldn't find `{}` in the chat of a custom game {} played since registering; replays can take a minute to appear", v.code, v.username)),
+            Err(e) => Some(e),
+        };
+        if let Some(error) = error {
+            i.create_followup_message(&ctx.http, |msg| {
+                msg.add_embed(embeds::error(Some("Register Error"), error)).flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+            }).await?;
+            return Ok(())
+        }
+
+        db.set_link_change(v.discord, crate::unix_time()).await.map_err(|e| e.to_string())?;
+        db.remove_verification(v.discord).await.map_err(|e| e.to_string())?;
+
+        if let (Some(guild), Some(member)) = (i.guild_id, &i.member) {
+            sync_roles(&ctx, guild, member, Some(&v.username)).await;
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title(if previous.is_some() { "Relinked" } else { "Registered" })
+            .description(format!("Username: {}\nDiscord: <@{}>", v.username, v.discord))
+            .color(palette::EMBED_GAME);
+        i.edit_original_interaction_response(&ctx.http, |resp| resp.add_embed(embed).components(|c| c)).await?;
+
+        Ok(())
+    })
+}
+
+/// Gives `member` the tier roles of `username`, or takes them all away if
+/// they're no longer registered.
+pub(crate) async fn sync_roles(ctx: &Context, guild: GuildId, member: &Member, username: Option<&str>) {
+    let synced = match (DB.get().await.get_role_tiers(guild.0).await.map_err(|e| e.to_string()), username) {
+        (Ok(tiers), Some(username)) => roles::sync_member(&ctx.http, guild, member, username, &tiers).await.map_err(|e| e.to_string()),
+        (Ok(tiers), None) => roles::clear_member(&ctx.http, guild, member, &tiers).await.map_err(|e| e.to_string()),
+        (Err(e), _) => Err(e),
+    };
+    if let Err(e) = synced {
+        eprintln!("roles: failed to sync {} in {}: {}", member.user.id, guild, e);
+    }
+}
+
+fn handle_unregister(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let db = DB.get().await;
+        let username = db.remove_username(i.user.id.0).await?;
+        let username = match username {
+            Some(username) => username,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Unregister Error"), "you aren't registered")).await?;
+                return Ok(())
+            }
+        };
+        let now = crate::unix_time();
+        db.set_link_change(i.user.id.0, now).await?;
+
+        if let (Some(guild), Some(member)) = (i.guild_id, &i.member) {
+            sync_roles(&ctx, guild, member, None).await;
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Unregistered")
+            .description(format!("<@{}> is no longer linked to {}. You can register again <t:{}:R>.", i.user.id, username, now + RELINK_COOLDOWN))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_UNREGISTER: Command = Command {
+        name: "unregister".into(),
+        description: "unlinks your generals.io username".into(),
+        args: Vec::new(),
+        handler: handle_unregister,
+        subcommands: Vec::new(),
+    };
+    pub static ref COMMAND_REGISTER: Command = Command {
+        name: "register".into(),
+        description: "registers generals.io username to discord user, or relinks to a new one".into(),
+        args: vec![
+            Arg { name: "username".into(), description: "generals.io username".into(), required: true, type_: TypeId::of::<String>() }
+        ],
+        handler: handle_register,
+        subcommands: Vec::new(),
+    };
+}
@@ -0,0 +1,310 @@
+//! `/replay` commands
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{embeds, palette, replay::{self, game::Game, render, stats, replay::Turn}};
+use super::{Arg, Command};
+
+/// Discord's upload limit for servers without boosts.
+const MAX_UPLOAD_BYTES: usize = 8 * 1024 * 1024;
+const MAX_CLIP_TURNS: u64 = 500;
+const CHAT_PAGE_SIZE: usize = 20;
+/// Longest chat message shown in full on a page; the attached transcript has the rest.
+const MAX_CHAT_MESSAGE: usize = 150;
+/// Discord's limit on embed descriptions, in characters.
+const MAX_DESCRIPTION: usize = 4096;
+
+fn handle_view(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut id = String::new();
+    let mut turn = None;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("id", Some(value)) => id.push_str(value.as_str().unwrap()),
+            ("turn", Some(value)) => turn = value.as_u64(),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        embeds::defer(&ctx, &i).await?;
+
+        let replay = match replay::fetch(&id).await.map_err(|e| e.to_string()) {
+            Ok(replay) => replay,
+            Err(e) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Replay Error"), e), None).await?;
+                return Ok(())
+            }
+        };
+
+        // replays count half-turns
+        let last = Game::last_turn(&replay);
+        let tick = turn.map(|t| (t as Turn).saturating_mul(2)).unwrap_or(last).min(last);
+        let (id, turn, png) = tokio::task::spawn_blocking(move || {
+            let game = Game::at(&replay, tick);
+            let png = render::draw(&game).to_png().map_err(|e| e.to_string());
+            (replay.id.clone(), game.turn(), png)
+        }).await?;
+        let png = png?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Replay: {}", id))
+            .url(format!("https://generals.io/replays/{}", id))
+            .description(format!("**Turn**: {}", turn / 2))
+            .image("attachment://board.png")
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, Some((png, "board.png"))).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_clip(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut id = String::new();
+    let (mut start, mut end) = (0, 0);
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("id", Some(value)) => id.push_str(value.as_str().unwrap()),
+            ("start", Some(value)) => start = value.as_u64().unwrap_or(0),
+            ("end", Some(value)) => end = value.as_u64().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        if end <= start || end - start > MAX_CLIP_TURNS {
+            embeds::respond(&ctx, &i, embeds::error(Some("Replay Error"),
+                format!("clips must end after they start and be at most {} turns long", MAX_CLIP_TURNS))).await?;
+            return Ok(())
+        }
+
+        embeds::defer(&ctx, &i).await?;
+
+        let replay = match replay::fetch(&id).await.map_err(|e| e.to_string()) {
+            Ok(replay) => replay,
+            Err(e) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Replay Error"), e), None).await?;
+                return Ok(())
+            }
+        };
+
+        let last = Game::last_turn(&replay);
+        let (start, end) = ((start as Turn).saturating_mul(2).min(last), (end as Turn).saturating_mul(2).min(last));
+        let id = replay.id.clone();
+        let gif = tokio::task::spawn_blocking(move || render::animate(&replay, start, end, MAX_UPLOAD_BYTES).map_err(|e| e.to_string()))
+            .await??;
+
+        let gif = match gif {
+            Some(gif) => gif,
+            None => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Replay Error"), "map is too large to animate"), None).await?;
+                return Ok(())
+            }
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Replay: {}", id))
+            .url(format!("https://generals.io/replays/{}", id))
+            .description(format!("**Turns**: {}–{}", start / 2, end / 2))
+            .image("attachment://clip.gif")
+            .color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, Some((gif, "clip.gif"))).await?;
+
+        Ok(())
+    })
+}
+
+/// Draws `values` as a row of block characters.
+fn sparkline(values: &[u64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    let chunk = values.len().div_ceil(width).max(1);
+    values.chunks(chunk)
+        .map(|c| BARS[(c.iter().copied().max().unwrap_or(0) * 7 / max) as usize])
+        .collect()
+}
+
+fn handle_stats(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut id = String::new();
+    for opt in &i.data.options {
+        if let ("id", Some(value)) = (opt.name.as_str(), &opt.value) {
+            id.push_str(value.as_str().unwrap());
+        }
+    }
+
+    Box::pin(async move {
+        embeds::defer(&ctx, &i).await?;
+
+        let replay = match replay::fetch(&id).await.map_err(|e| e.to_string()) {
+            Ok(replay) => replay,
+            Err(e) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Replay Error"), e), None).await?;
+                return Ok(())
+            }
+        };
+        // simulating a long replay is CPU-bound
+        let (replay, stats) = tokio::task::spawn_blocking(move || {
+            let stats = stats::compute(&replay);
+            (replay, stats)
+        }).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Replay Stats: {}", replay.id))
+            .url(format!("https://generals.io/replays/{}", replay.id))
+            .description(format!("**Turns**: {}", Game::last_turn(&replay) / 2))
+            .color(palette::EMBED_GAME);
+        for (p, s) in stats.iter().enumerate() {
+            let mut lines = vec![
+                format!("**Army**: {} (peak {})", s.army.last().unwrap_or(&0), s.peak_army),
+                format!("`{}`", sparkline(&s.army, 20)),
+                format!("**Land**: {}", s.land.last().unwrap_or(&0)),
+                format!("**Cities held**: {}", s.peak_cities),
+                format!("**Moves/turn**: {:.2}", s.moves_per_turn()),
+                format!("**Idle turns**: {}", s.idle_turns),
+                format!("**First contact**: {}", s.first_contact.map(|t| t.to_string()).unwrap_or("---".to_string())),
+            ];
+            if let Some(turn) = s.eliminated {
+                let killer = s.killer.and_then(|k| replay.usernames.get(k as usize)).map(|k| k.as_str()).unwrap_or("?");
+                lines.push(format!("**Eliminated**: turn {} by {}", turn, killer));
+            }
+            if let Some(turn) = s.afk {
+                lines.push(format!("**AFK**: turn {}", turn));
+            }
+            embed.field(replay.usernames.get(p).map(|u| u.as_str()).unwrap_or("?"), lines.join("\n"), true);
+        }
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        Ok(())
+    })
+}
+
+/// Escapes Discord markdown in player-written text.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '~' | '`' | '|' | '>' | '\\' | '@' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn handle_chat(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut id = String::new();
+    let mut page = 1;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("id", Some(value)) => id.push_str(value.as_str().unwrap()),
+            ("page", Some(value)) => page = value.as_u64().unwrap_or(1).max(1) as usize,
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        embeds::defer(&ctx, &i).await?;
+
+        let replay = match replay::fetch(&id).await.map_err(|e| e.to_string()) {
+            Ok(replay) => replay,
+            Err(e) => {
+                embeds::followup(&ctx, &i, embeds::error(Some("Replay Error"), e), None).await?;
+                return Ok(())
+            }
+        };
+
+        let name = |msg: &replay::replay::ChatMessage| if msg.prefix.is_empty() {
+            replay.usernames.get(msg.player_index as usize).cloned().unwrap_or_default()
+        } else {
+            msg.prefix.clone()
+        };
+
+        let pages = replay.chat.len().div_ceil(CHAT_PAGE_SIZE).max(1);
+        let page = page.min(pages);
+        let mut lines = Vec::new();
+        let mut length = 0;
+        for msg in replay.chat.iter().skip((page - 1) * CHAT_PAGE_SIZE).take(CHAT_PAGE_SIZE) {
+            let color = replay.player_colors.get(msg.player_index as usize).copied().unwrap_or(msg.player_index as u32);
+            let mut message = msg.message.chars().take(MAX_CHAT_MESSAGE).collect::<String>();
+            if message.len() < msg.message.len() {
+                message.push('…');
+            }
+            let line = format!("{} `T{:>4}` **{}**: {}",
+                palette::PLAYER_EMOJI[color as usize % palette::PLAYER_EMOJI.len()],
+                msg.turn / 2, escape(&name(msg)), escape(&message));
+            // count the joining newline, and leave room for the ellipsis line
+            length += line.chars().count() + 1;
+            if length > MAX_DESCRIPTION - 2 {
+                lines.push("…".to_string());
+                break;
+            }
+            lines.push(line);
+        }
+
+        let transcript = replay.chat.iter()
+            .map(|msg| format!("[turn {}] {}: {}", msg.turn / 2, name(msg), msg.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Replay Chat: {}", replay.id))
+            .url(format!("https://generals.io/replays/{}", replay.id))
+            .description(if lines.is_empty() { "*no messages*".to_string() } else { lines.join("\n") })
+            .footer(|f| f.text(format!("Page {}/{}", page, pages)))
+            .color(palette::EMBED_GAME);
+        let file = if replay.chat.is_empty() { None } else { Some((transcript.into_bytes(), "chat.txt")) };
+        embeds::followup(&ctx, &i, embed, file).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_REPLAY: Command = Command::group("replay", "generals.io replay tools", vec![
+        Command {
+            name: "view".into(),
+            description: "shows the board of a replay".into(),
+            args: vec![
+                Arg { name: "id".into(), description: "replay ID".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "turn".into(), description: "turn to show (defaults to the end)".into(), required: false, type_: TypeId::of::<u32>() },
+            ],
+            handler: handle_view,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "clip".into(),
+            description: "animates part of a replay".into(),
+            args: vec![
+                Arg { name: "id".into(), description: "replay ID".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "start".into(), description: "first turn".into(), required: true, type_: TypeId::of::<u32>() },
+                Arg { name: "end".into(), description: "last turn".into(), required: true, type_: TypeId::of::<u32>() },
+            ],
+            handler: handle_clip,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "stats".into(),
+            description: "shows per-player statistics for a replay".into(),
+            args: vec![
+                Arg { name: "id".into(), description: "replay ID".into(), required: true, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_stats,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "chat".into(),
+            description: "shows the in-game chat of a replay".into(),
+            args: vec![
+                Arg { name: "id".into(), description: "replay ID".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "page".into(), description: "page number".into(), required: false, type_: TypeId::of::<u32>() },
+            ],
+            handler: handle_chat,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
@@ -0,0 +1,171 @@
+//! `/roles` commands for star tier roles
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::{guild::Role, interactions::application_command::ApplicationCommandInteraction}};
+
+use crate::{embeds, palette, roles::{self, RoleTier}, stars::Mode, DB};
+use super::{admin_guild, Arg, Command};
+
+fn handle_add(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut role = 0;
+    let mut mode = String::new();
+    let mut stars = 0.0;
+    let mut alltime = false;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("role", Some(value)) => role = value.as_str().unwrap().parse().unwrap_or(0),
+            ("mode", Some(value)) => mode.push_str(value.as_str().unwrap()),
+            ("stars", Some(value)) => stars = value.as_f64().unwrap_or(0.0),
+            ("alltime", Some(value)) => alltime = value.as_bool().unwrap_or(false),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let guild = match admin_guild(&ctx, &i, "Roles Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+        let mode: Mode = match mode.parse() {
+            Ok(mode) => mode,
+            Err(e) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Roles Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+
+        let tier = RoleTier { role, mode, stars, alltime };
+        DB.get().await.add_role_tier(guild, &tier).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Role Tier Added")
+            .description(format!("{} {}{}+ → <@&{}>", mode, if alltime { "all-time " } else { "" }, stars, role))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_remove(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let mut role = 0;
+    for opt in &i.data.options {
+        if let ("role", Some(value)) = (opt.name.as_str(), &opt.value) {
+            role = value.as_str().unwrap().parse().unwrap_or(0);
+        }
+    }
+
+    Box::pin(async move {
+        let guild = match admin_guild(&ctx, &i, "Roles Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+
+        if !DB.get().await.remove_role_tier(guild, role).await? {
+            embeds::respond(&ctx, &i, embeds::error(Some("Roles Error"), "role has no tier")).await?;
+            return Ok(())
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Role Tier Removed")
+            .description(format!("<@&{}> will no longer be managed", role))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_list(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Roles Error"), "roles are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        let tiers = DB.get().await.get_role_tiers(guild.0).await?;
+        let mut embed = CreateEmbed::default();
+        embed.title("Role Tiers")
+            .description(if tiers.is_empty() {
+                "*no role tiers configured*".to_string()
+            } else {
+                tiers.iter()
+                    .map(|t| format!("{} {}{}+ → <@&{}>", t.mode, if t.alltime { "all-time " } else { "" }, t.stars, t.role))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_sync(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match admin_guild(&ctx, &i, "Roles Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+
+        embeds::defer(&ctx, &i).await?;
+        roles::sync_guild(&ctx.http, guild.into()).await.map_err(|e| e.to_string())?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Roles Synced").color(palette::EMBED_GAME);
+        embeds::followup(&ctx, &i, embed, None).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_ROLES: Command = Command::group("roles", "roles from star tiers", vec![
+        Command {
+            name: "add".into(),
+            description: "gives a role to registered members above a star threshold".into(),
+            args: vec![
+                Arg { name: "role".into(), description: "role to give".into(), required: true, type_: TypeId::of::<Role>() },
+                Arg { name: "mode".into(), description: "ffa, duel or 2v2".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "stars".into(), description: "minimum stars".into(), required: true, type_: TypeId::of::<f64>() },
+                Arg { name: "alltime".into(), description: "use all-time stars".into(), required: false, type_: TypeId::of::<bool>() },
+            ],
+            handler: handle_add,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "remove".into(),
+            description: "stops managing a role".into(),
+            args: vec![
+                Arg { name: "role".into(), description: "role to stop managing".into(), required: true, type_: TypeId::of::<Role>() },
+            ],
+            handler: handle_remove,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "list".into(),
+            description: "lists role tiers".into(),
+            args: Vec::new(),
+            handler: handle_list,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "sync".into(),
+            description: "syncs roles now instead of waiting for the hourly sync".into(),
+            args: Vec::new(),
+            handler: handle_sync,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
@@ -0,0 +1,128 @@
+//! `/season` commands for seasonal leagues
+
+use std::{any::TypeId, pin::Pin};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+
+use crate::{embeds, palette, seasons::Season, DB};
+use super::{admin_guild, lobby, Arg, Command};
+
+fn handle_create(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let (mut name, mut start, mut end) = (String::new(), String::new(), String::new());
+    let mut soft_reset = false;
+    for opt in &i.data.options {
+        match (opt.name.as_str(), &opt.value) {
+            ("name", Some(value)) => name.push_str(value.as_str().unwrap()),
+            ("start", Some(value)) => start.push_str(value.as_str().unwrap()),
+            ("end", Some(value)) => end.push_str(value.as_str().unwrap()),
+            ("soft_reset", Some(value)) => soft_reset = value.as_bool().unwrap_or(false),
+            _ => {}
+        }
+    }
+
+    Box::pin(async move {
+        let guild = match admin_guild(&ctx, &i, "Season Error").await? {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+
+        let now = crate::unix_time();
+        let (start, end) = match (lobby::parse_time(&start, now), lobby::parse_time(&end, now)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Season Error"), "times must be unix timestamps, Discord timestamps or delays like `30d`")).await?;
+                return Ok(())
+            }
+        };
+
+        let db = DB.get().await;
+        let seasons = db.get_seasons(guild).await?;
+        let error = match seasons.last() {
+            _ if end <= start => Some("a season must end after it starts".to_string()),
+            _ if end <= now => Some("a season must end in the future".to_string()),
+            Some(last) if start < last.end => Some(format!("seasons can't overlap; season {} ends <t:{}:F>", last.number, last.end)),
+            _ => None,
+        };
+        if let Some(error) = error {
+            embeds::respond(&ctx, &i, embeds::error(Some("Season Error"), error)).await?;
+            return Ok(())
+        }
+
+        let season = Season {
+            guild,
+            number: seasons.last().map(|s| s.number).unwrap_or(0) + 1,
+            name,
+            start,
+            end,
+            soft_reset,
+            archived: false,
+        };
+        db.add_season(&season).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Season {}: {}", season.number, season.name))
+            .description(format!("<t:{}:F> – <t:{}:F>\nStandings are archived when the season ends.{}",
+                season.start, season.end, if soft_reset { "\nServer ratings are soft reset when it starts." } else { "" }))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_list(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild.0,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Season Error"), "seasons are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        let now = crate::unix_time();
+        let seasons = DB.get().await.get_seasons(guild).await?;
+        let lines = seasons.iter().rev()
+            .map(|s| {
+                let status = if s.archived || now >= s.end { "ended" } else if now >= s.start { "**running**" } else { "upcoming" };
+                format!("**{}.** {} — <t:{}:d> – <t:{}:d> ({})", s.number, s.name, s.start, s.end, status)
+            })
+            .collect::<Vec<_>>();
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Seasons")
+            .description(if lines.is_empty() { "*no seasons yet*".to_string() } else { lines.join("\n") })
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_SEASON: Command = Command::group("season", "seasonal leagues", vec![
+        Command {
+            name: "create".into(),
+            description: "schedules a new season".into(),
+            args: vec![
+                Arg { name: "name".into(), description: "season name".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "start".into(), description: "unix time, Discord timestamp or delay like 2d".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "end".into(), description: "unix time, Discord timestamp or delay like 30d".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "soft_reset".into(), description: "pull server ratings toward the default when the season starts".into(), required: false, type_: TypeId::of::<bool>() },
+            ],
+            handler: handle_create,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "list".into(),
+            description: "lists this server's seasons".into(),
+            args: Vec::new(),
+            handler: handle_list,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
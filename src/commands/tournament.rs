@@ -0,0 +1,673 @@
+//! `/tournament` commands
+
+use std::{any::TypeId, collections::HashMap, pin::Pin, time::Duration};
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, http::Http, model::{id::ChannelId, interactions::application_command::ApplicationCommandInteraction, user::User}};
+
+use crate::{embeds, palette, ratings::{self, RatedGame}, replay::{self, metadata}, rooms, stars::{self, Mode}, tournament::{self, bracket::{self, Bracket, MatchId, Slot}, swiss::{self, Standing}, Entrant, Format, MatchResult, ScheduledMatch, State, Tournament}, DB};
+use super::{lobby, Arg, Command};
+
+/// Delay between star lookups when seeding.
+const SEED_DELAY: Duration = Duration::from_millis(500);
+/// Delay between replay lookups when verifying matches.
+const VERIFY_DELAY: Duration = Duration::from_secs(1);
+/// How long after its scheduled time a match is watched for a replay.
+const VERIFY_WINDOW: u64 = 24 * 60 * 60;
+/// Replays per player searched for a scheduled match.
+const VERIFY_GAMES: usize = 20;
+const MAX_FIELD_LEN: usize = 1024;
+const MAX_FIELDS: usize = 25;
+
+/// Options of `i` as (name, value) for simple lookups.
+fn options(i: &ApplicationCommandInteraction) -> HashMap<String, serde_json::Value> {
+    i.data.options.iter()
+        .filter_map(|opt| opt.value.clone().map(|v| (opt.name.clone(), v)))
+        .collect()
+}
+
+/// Whether the user who ran `i` may run tournament `t`.
+fn can_manage(i: &ApplicationCommandInteraction, t: &Tournament) -> bool {
+    i.user.id.0 == t.creator || crate::is_admin(i)
+}
+
+/// Rebuilds the bracket of `t` from its stored results, with standings for Swiss tournaments.
+pub fn build_bracket(t: &Tournament, entrants: &[Entrant], results: &[MatchResult]) -> (Bracket, Option<Vec<Standing>>) {
+    let results = results.iter()
+        .filter_map(|r| Some((r.id.parse::<MatchId>().ok()?, entrants.iter().position(|e| e.discord == r.winner)?)))
+        .collect();
+    match t.format {
+        Format::Swiss => {
+            let (bracket, standings) = swiss::build(entrants.len(), t.rounds, &results);
+            (bracket, Some(standings))
+        },
+        _ => (Bracket::build(t.format, entrants.len(), &results), None),
+    }
+}
+
+/// Bracket image, which Swiss tournaments don't have.
+fn bracket_image(bracket: &Bracket) -> crate::Result<Option<(Vec<u8>, &'static str)>> {
+    Ok(match bracket.format {
+        Format::Swiss => None,
+        _ => Some((bracket::draw(bracket).to_png()?, "bracket.png")),
+    })
+}
+
+fn slot_name(slot: Slot, entrants: &[Entrant]) -> String {
+    match slot {
+        Slot::Player(p) => entrants[p].username.clone(),
+        Slot::Bye => "*bye*".to_string(),
+        Slot::Pending => "*TBD*".to_string(),
+    }
+}
+
+/// Appends `line` to `text` unless that would go over an embed field's limit.
+fn push_line(text: &mut String, line: &str) {
+    if text.len() + line.len() + 2 > MAX_FIELD_LEN {
+        if !text.ends_with('…') {
+            text.push('…');
+        }
+    } else {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+    }
+}
+
+fn bracket_embed(t: &Tournament, entrants: &[Entrant], bracket: &Bracket, standings: Option<&[Standing]>) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("#{} {}", t.id, t.name))
+        .description(match bracket.champion {
+            Slot::Player(p) => format!("🏆 **{}** won the tournament!", entrants[p].username),
+            _ if t.format == Format::Swiss => format!("{}, {} rounds, {} seeding", t.format.name(), t.rounds, t.mode),
+            _ => format!("{}, {} seeding", t.format.name(), t.mode),
+        })
+        .color(palette::EMBED_GAME);
+
+    match standings {
+        Some(standings) => {
+            let mut lines = String::new();
+            for (k, s) in standings.iter().enumerate() {
+                push_line(&mut lines, &format!("`{}.` {} — **{}** pts (Buchholz {}, SB {})",
+                    k + 1, entrants[s.player].username, s.score, s.buchholz, s.sonneborn_berger));
+            }
+            embed.field("Standings", lines, false);
+        },
+        None => {
+            let mut seeds = String::new();
+            for (k, e) in entrants.iter().enumerate() {
+                push_line(&mut seeds, &format!("`{}` {} ({:.0}★) <@{}>", k + 1, e.username, e.stars, e.discord));
+            }
+            embed.field("Seeds", seeds, false).image("attachment://bracket.png");
+        }
+    }
+
+    for (side, round) in bracket.rounds().into_iter().take(MAX_FIELDS - 1) {
+        let mut lines = String::new();
+        for m in bracket.matches.iter().filter(|m| m.id.side == side && m.id.round == round) {
+            let name = |slot: Slot| {
+                let name = slot_name(slot, entrants);
+                if slot == m.winner && matches!(slot, Slot::Player(_)) { format!("**{}**", name) } else { name }
+            };
+            push_line(&mut lines, &format!("`{}` {} vs {}", m.id, name(m.a), name(m.b)));
+        }
+        embed.field(bracket.round_name(side, round), lines, true);
+    }
+    embed
+}
+
+/// Stores `result`, finishing the tournament if it decided the champion,
+/// and describes the result along with the matches it unlocked.
+async fn record_result(t: &Tournament, entrants: &[Entrant], mut results: Vec<MatchResult>, result: MatchResult) -> crate::Result<String> {
+    let db = DB.get().await;
+    db.add_result(t.id, &result).await?;
+    let match_id = result.id.parse::<MatchId>()?;
+    let replay = result.replay.clone();
+    results.push(result);
+
+    let (bracket, _) = build_bracket(t, entrants, &results);
+    let reported = bracket.get(match_id).ok_or("no such match")?;
+    let mut description = format!("`{}` **{}** beat {}", match_id, slot_name(reported.winner, entrants), slot_name(reported.loser, entrants));
+    if let Some(replay) = replay {
+        description += &format!(" ([replay](https://generals.io/replays/{}))", replay);
+    }
+    match bracket.champion {
+        Slot::Player(p) => {
+            db.set_tournament_state(t.id, State::Finished).await?;
+            description += &format!("\n\n🏆 **{}** won the tournament!", entrants[p].username);
+        },
+        _ => {
+            let next = bracket.matches.iter()
+                .filter(|m| m.is_playable() && (m.has_slot(reported.winner) || m.has_slot(reported.loser)))
+                .map(|m| format!("`{}` {} vs {}", m.id, slot_name(m.a, entrants), slot_name(m.b, entrants)))
+                .collect::<Vec<_>>();
+            if !next.is_empty() {
+                description += &format!("\n\n**Next up**\n{}", next.join("\n"));
+            }
+        }
+    }
+    Ok(description)
+}
+
+/// Looks up tournament `id` in the guild `i` was run in, responding with an error if there is none.
+async fn find(ctx: &Context, i: &ApplicationCommandInteraction, id: u64) -> crate::Result<Option<Tournament>> {
+    let t = DB.get().await.get_tournament(id).await?;
+    match t {
+        Some(t) if Some(t.guild) == i.guild_id.map(|g| g.0) => Ok(Some(t)),
+        _ => {
+            embeds::respond(ctx, i, embeds::error(Some("Tournament Error"), "no such tournament in this server")).await?;
+            Ok(None)
+        }
+    }
+}
+
+fn handle_create(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let opts = options(&i);
+    let name = opts.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let format = opts.get("format").and_then(|v| v.as_str()).unwrap_or("single").to_string();
+    let mode = opts.get("mode").and_then(|v| v.as_str()).unwrap_or("duel").to_string();
+    let rounds = opts.get("rounds").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "tournaments are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+        let (format, mode) = match (format.parse::<Format>(), mode.parse::<Mode>()) {
+            (Ok(format), Ok(mode)) => (format, mode),
+            (Err(e), _) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), format!("{}", e))).await?;
+                return Ok(())
+            },
+            (_, Err(e)) => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), format!("{}", e))).await?;
+                return Ok(())
+            }
+        };
+
+        let mut t = Tournament {
+            id: 0,
+            guild: guild.0,
+            name,
+            format,
+            mode,
+            state: State::Registration,
+            rounds,
+            creator: i.user.id.0,
+            created: crate::unix_time(),
+        };
+        t.id = DB.get().await.add_tournament(&t).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("#{} {}", t.id, t.name))
+            .description(format!("{} tournament, seeded by {} stars.\nJoin with `/tournament join id:{}`", t.format.name(), t.mode, t.id))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_join(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let id = options(&i).get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    Box::pin(async move {
+        let t = match find(&ctx, &i, id).await? {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        if t.state != State::Registration {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "registration is closed")).await?;
+            return Ok(())
+        }
+
+        let db = DB.get().await;
+        let username = db.get_username(i.user.id.0).await?;
+        let username = match username {
+            Some(username) => username,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "link your generals.io account with `/register` first")).await?;
+                return Ok(())
+            }
+        };
+        if !db.add_entrant(t.id, i.user.id.0, &username).await? {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "you already joined")).await?;
+            return Ok(())
+        }
+
+        let count = db.get_entrants(t.id).await?.len();
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("#{} {}", t.id, t.name))
+            .description(format!("**{}** joined ({} entrants)", username, count))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_leave(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let id = options(&i).get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    Box::pin(async move {
+        let t = match find(&ctx, &i, id).await? {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        if t.state != State::Registration {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "the tournament has already started")).await?;
+            return Ok(())
+        }
+        if !DB.get().await.remove_entrant(t.id, i.user.id.0).await? {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "you haven't joined")).await?;
+            return Ok(())
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("#{} {}", t.id, t.name))
+            .description(format!("<@{}> left", i.user.id))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_start(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let id = options(&i).get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    Box::pin(async move {
+        let mut t = match find(&ctx, &i, id).await? {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        let db = DB.get().await;
+        let mut entrants = db.get_entrants(t.id).await?;
+        let error = if !can_manage(&i, &t) {
+            Some("only the organiser can start the tournament")
+        } else if t.state != State::Registration {
+            Some("the tournament has already started")
+        } else if entrants.len() < 2 {
+            Some("at least two entrants are needed")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), error)).await?;
+            return Ok(())
+        }
+
+        embeds::defer(&ctx, &i).await?;
+
+        for e in entrants.iter_mut() {
+            let fetched = stars::fetch(&e.username).await.map_err(|e| e.to_string());
+            match fetched {
+                Ok(s) => e.stars = s.get(t.mode, false).unwrap_or(0.0),
+                Err(err) => eprintln!("tournament {}: stars for {}: {}", t.id, e.username, err),
+            }
+            tokio::time::sleep(SEED_DELAY).await;
+        }
+        entrants.sort_by(|a, b| b.stars.partial_cmp(&a.stars).unwrap_or(std::cmp::Ordering::Equal));
+        db.set_seeds(t.id, &entrants).await.map_err(|e| e.to_string())?;
+        db.set_tournament_state(t.id, State::Running).await.map_err(|e| e.to_string())?;
+        if t.format == Format::Swiss && t.rounds == 0 {
+            // enough rounds for a single undefeated player
+            t.rounds = entrants.len().next_power_of_two().trailing_zeros();
+            db.set_tournament_rounds(t.id, t.rounds).await.map_err(|e| e.to_string())?;
+        }
+
+        let (bracket, standings) = build_bracket(&t, &entrants, &[]);
+        let image = bracket_image(&bracket)?;
+        embeds::followup(&ctx, &i, bracket_embed(&t, &entrants, &bracket, standings.as_deref()), image).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_report(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let opts = options(&i);
+    let id = opts.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+    let winner = opts.get("winner").and_then(|v| v.as_str()).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let replay = opts.get("replay").and_then(|v| v.as_str()).map(|v| v.rsplit('/').next().unwrap_or(v).to_string());
+
+    Box::pin(async move {
+        let t = match find(&ctx, &i, id).await? {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        let db = DB.get().await;
+        let entrants = db.get_entrants(t.id).await?;
+        let results = db.get_results(t.id).await?;
+        let (bracket, _) = build_bracket(&t, &entrants, &results);
+
+        let m = entrants.iter().position(|e| e.discord == winner).and_then(|p| bracket.next_match(p));
+        let error = match m {
+            _ if t.state != State::Running => Some("the tournament isn't running"),
+            None => Some("that player has no match to report"),
+            Some(m) if !can_manage(&i, &t) && !m.has(entrants.iter().position(|e| e.discord == i.user.id.0).unwrap_or(usize::MAX)) =>
+                Some("only the organiser or the players can report a match"),
+            _ => None,
+        };
+        if let Some(error) = error {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), error)).await?;
+            return Ok(())
+        }
+        let m = m.unwrap();
+
+        let result = MatchResult { id: m.id.to_string(), winner, replay };
+        let description = record_result(&t, &entrants, results, result).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("#{} {}", t.id, t.name))
+            .description(description)
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_schedule(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let opts = options(&i);
+    let id = opts.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+    let player = opts.get("player").and_then(|v| v.as_str()).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let time = opts.get("time").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let code = opts.get("code").and_then(|v| v.as_str()).map(|v| v.to_string());
+
+    Box::pin(async move {
+        let t = match find(&ctx, &i, id).await? {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        let db = DB.get().await;
+        let entrants = db.get_entrants(t.id).await?;
+        let results = db.get_results(t.id).await?;
+        let (bracket, _) = build_bracket(&t, &entrants, &results);
+
+        let time = lobby::parse_time(&time, crate::unix_time());
+        let m = entrants.iter().position(|e| e.discord == player).and_then(|p| bracket.next_match(p));
+        let error = match (m, time) {
+            _ if t.state != State::Running => Some("the tournament isn't running"),
+            (None, _) => Some("that player has no match to schedule"),
+            (Some(m), _) if !can_manage(&i, &t) && !m.has(entrants.iter().position(|e| e.discord == i.user.id.0).unwrap_or(usize::MAX)) =>
+                Some("only the organiser or the players can schedule a match"),
+            (_, None) => Some("invalid time, use e.g. `2h30m` or a unix timestamp"),
+            _ => None,
+        };
+        if let Some(error) = error {
+            embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), error)).await?;
+            return Ok(())
+        }
+        let (m, time) = (m.unwrap(), time.unwrap());
+
+        let scheduled = ScheduledMatch {
+            tournament: t.id,
+            id: m.id.to_string(),
+            channel: i.channel_id.0,
+            time,
+            code: code.unwrap_or_else(rooms::random_code),
+            done: false,
+        };
+        db.add_scheduled_match(&scheduled).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("#{} {}: {}", t.id, t.name, scheduled.id))
+            .url(rooms::room_url(&scheduled.code, None, None))
+            .description(format!("{} vs {}\n**Start:** <t:{}:F>\n**Room:** {}\n\nType `{}` in the game chat so the result can be recorded from the replay.",
+                slot_name(m.a, &entrants), slot_name(m.b, &entrants), time, rooms::room_url(&scheduled.code, None, None), scheduled.code))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+/// Records results of scheduled matches from the players' replays.
+pub async fn verify_matches(http: &Http) -> crate::Result<()> {
+    let db = DB.get().await;
+    let now = crate::unix_time();
+    let scheduled = db.get_pending_scheduled_matches().await?;
+    for scheduled in scheduled.into_iter().filter(|s| s.time <= now) {
+        let t = db.get_tournament(scheduled.tournament).await?;
+        let t = match t {
+            Some(t) if t.state == State::Running => t,
+            _ => {
+                db.set_scheduled_match_done(scheduled.tournament, &scheduled.id).await?;
+                continue;
+            }
+        };
+        let entrants = db.get_entrants(t.id).await?;
+        let results = db.get_results(t.id).await?;
+        let (bracket, _) = build_bracket(&t, &entrants, &results);
+
+        // reported by hand in the meantime
+        let players = match scheduled.id.parse().ok().and_then(|id| bracket.get(id)).map(|m| (m.a, m.b, m.winner)) {
+            Some((Slot::Player(a), Slot::Player(b), Slot::Pending)) => (a, b),
+            _ => {
+                db.set_scheduled_match_done(t.id, &scheduled.id).await?;
+                continue;
+            }
+        };
+
+        // either player's feed will do, but one may have hidden or lost theirs
+        let (a, b) = (&entrants[players.0].username, &entrants[players.1].username);
+        let mut game = None;
+        for username in [a, b].iter() {
+            tokio::time::sleep(VERIFY_DELAY).await;
+            let games = metadata::fetch(username, 0, VERIFY_GAMES).await.map_err(|e| e.to_string());
+            let games = match games {
+                Ok(games) => games,
+                Err(e) => {
+                    eprintln!("tournament {}: replays for {}: {}", t.id, username, e);
+                    continue;
+                }
+            };
+            for g in tournament::candidate_games(&games, a, b, scheduled.time) {
+                tokio::time::sleep(VERIFY_DELAY).await;
+                let played = replay::fetch(&g.id).await.map(|r| tournament::played_in(&r, &scheduled.code)).map_err(|e| e.to_string());
+                match played {
+                    Ok(true) => {
+                        let rated = match db.get_users().await.map_err(|e| e.to_string()) {
+                            Ok(users) => RatedGame::new(t.guild, g, None, &users),
+                            Err(e) => {
+                                eprintln!("tournament {}: rating {}: {}", t.id, g.id, e);
+                                None
+                            }
+                        };
+                        game = Some((g.id.clone(), g.ranking[0].name.clone(), g.ranking[0].current_name.clone(), rated));
+                        break;
+                    },
+                    Ok(false) => {},
+                    Err(e) => eprintln!("tournament {}: replay {}: {}", t.id, g.id, e),
+                }
+            }
+            if game.is_some() {
+                break;
+            }
+        }
+
+        let (replay, name, current_name, rated) = match game {
+            Some(game) => game,
+            None => {
+                if now > scheduled.time + VERIFY_WINDOW {
+                    eprintln!("tournament {}: no replay found for {}", t.id, scheduled.id);
+                    db.set_scheduled_match_done(t.id, &scheduled.id).await?;
+                }
+                continue;
+            }
+        };
+        let winner = if a.eq_ignore_ascii_case(&name) || a.eq_ignore_ascii_case(&current_name) { players.0 } else { players.1 };
+
+        let result = MatchResult { id: scheduled.id.clone(), winner: entrants[winner].discord, replay: Some(replay) };
+        let description = record_result(&t, &entrants, results, result).await?;
+        db.set_scheduled_match_done(t.id, &scheduled.id).await?;
+        eprintln!("tournament {}: verified {} from replay", t.id, scheduled.id);
+
+        // the result stands even if the rating can't be recorded
+        if let Some(rated) = rated {
+            if let Err(e) = ratings::record(&rated).await.map_err(|e| e.to_string()) {
+                eprintln!("tournament {}: rating {}: {}", t.id, rated.id, e);
+            }
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("#{} {}", t.id, t.name))
+            .description(description)
+            .footer(|f| f.text("Result recorded from the replay"))
+            .color(palette::EMBED_GAME);
+        let sent = ChannelId(scheduled.channel).send_message(http, |m| m.set_embed(embed)).await;
+        if let Err(e) = sent {
+            eprintln!("tournament {}: {}", t.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_bracket(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+    let id = options(&i).get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    Box::pin(async move {
+        let t = match find(&ctx, &i, id).await? {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        let db = DB.get().await;
+        let entrants = db.get_entrants(t.id).await?;
+        if t.state == State::Registration {
+            let mut embed = CreateEmbed::default();
+            embed.title(format!("#{} {}", t.id, t.name))
+                .description(format!("Registration open, {} entrants:\n{}", entrants.len(),
+                    entrants.iter().map(|e| e.username.as_str()).collect::<Vec<_>>().join(", ")))
+                .color(palette::EMBED_GAME);
+            embeds::respond(&ctx, &i, embed).await?;
+            return Ok(())
+        }
+
+        embeds::defer(&ctx, &i).await?;
+        let results = db.get_results(t.id).await.map_err(|e| e.to_string())?;
+        let (bracket, standings) = build_bracket(&t, &entrants, &results);
+        let image = bracket_image(&bracket)?;
+        embeds::followup(&ctx, &i, bracket_embed(&t, &entrants, &bracket, standings.as_deref()), image).await?;
+
+        Ok(())
+    })
+}
+
+fn handle_list(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        let guild = match i.guild_id {
+            Some(guild) => guild,
+            None => {
+                embeds::respond(&ctx, &i, embeds::error(Some("Tournament Error"), "tournaments are only available in servers")).await?;
+                return Ok(())
+            }
+        };
+
+        let tournaments = DB.get().await.get_tournaments(guild.0).await?;
+        let mut lines = String::new();
+        for t in &tournaments {
+            push_line(&mut lines, &format!("`#{}` **{}** — {}, {} (<t:{}:d>)", t.id, t.name, t.format.name(), t.state, t.created));
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Tournaments")
+            .description(if lines.is_empty() { "*no tournaments yet*".to_string() } else { lines })
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+fn id_arg() -> Arg {
+    Arg { name: "id".into(), description: "tournament number".into(), required: true, type_: TypeId::of::<u64>() }
+}
+
+lazy_static! {
+    pub static ref COMMAND_TOURNAMENT: Command = Command::group("tournament", "tournaments among registered members", vec![
+        Command {
+            name: "create".into(),
+            description: "creates a tournament open for registration".into(),
+            args: vec![
+                Arg { name: "name".into(), description: "tournament name".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "format".into(), description: "single, double or swiss (defaults to single)".into(), required: false, type_: TypeId::of::<String>() },
+                Arg { name: "mode".into(), description: "stars to seed by: ffa, duel or 2v2 (defaults to duel)".into(), required: false, type_: TypeId::of::<String>() },
+                Arg { name: "rounds".into(), description: "Swiss rounds (defaults to enough for one undefeated player)".into(), required: false, type_: TypeId::of::<u64>() },
+            ],
+            handler: handle_create,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "join".into(),
+            description: "enters a tournament with your registered account".into(),
+            args: vec![id_arg()],
+            handler: handle_join,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "leave".into(),
+            description: "withdraws from a tournament before it starts".into(),
+            args: vec![id_arg()],
+            handler: handle_leave,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "start".into(),
+            description: "closes registration, seeds by stars and generates the bracket".into(),
+            args: vec![id_arg()],
+            handler: handle_start,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "report".into(),
+            description: "reports the winner of a match".into(),
+            args: vec![
+                id_arg(),
+                Arg { name: "winner".into(), description: "player who won".into(), required: true, type_: TypeId::of::<User>() },
+                Arg { name: "replay".into(), description: "replay link or ID".into(), required: false, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_report,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "schedule".into(),
+            description: "schedules a player's next match in a custom room, recording the result from the replay".into(),
+            args: vec![
+                id_arg(),
+                Arg { name: "player".into(), description: "either player of the match".into(), required: true, type_: TypeId::of::<User>() },
+                Arg { name: "time".into(), description: "start time, e.g. 2h30m or a unix timestamp".into(), required: true, type_: TypeId::of::<String>() },
+                Arg { name: "code".into(), description: "custom room code (random if empty)".into(), required: false, type_: TypeId::of::<String>() },
+            ],
+            handler: handle_schedule,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "bracket".into(),
+            description: "shows a tournament's bracket".into(),
+            args: vec![id_arg()],
+            handler: handle_bracket,
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "list".into(),
+            description: "lists tournaments in this server".into(),
+            args: Vec::new(),
+            handler: handle_list,
+            subcommands: Vec::new(),
+        },
+    ]);
+}
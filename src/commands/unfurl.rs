@@ -0,0 +1,142 @@
+//! Replay link unfurling and the `/unfurl` command
+
+use std::pin::Pin;
+
+use futures::Future;
+use serenity::{builder::CreateEmbed, client::Context, http::AttachmentType, model::{channel::Message, interactions::application_command::ApplicationCommandInteraction}};
+
+use crate::{embeds, palette, replay::{self, render, stats}, DB};
+use super::{admin_guild, Command};
+
+/// Most links unfurled from a single message.
+const MAX_LINKS: usize = 3;
+
+/// Replay IDs linked in `content`.
+fn replay_ids(content: &str) -> Vec<&str> {
+    content.match_indices("generals.io/replays/")
+        .filter_map(|(k, prefix)| {
+            let rest = &content[k + prefix.len()..];
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+            if len == 0 { None } else { Some(&rest[..len]) }
+        })
+        .take(MAX_LINKS)
+        .collect()
+}
+
+/// Summary embed and final board thumbnail for replay `id`.
+async fn summarize(id: &str) -> crate::Result<(CreateEmbed, Vec<u8>)> {
+    let replay = replay::fetch(id).await?;
+    // one simulation gives both the placements and the final board
+    let (replay, placements, turn, png) = tokio::task::spawn_blocking(move || {
+        let (placements, turn, png) = {
+            let (stats, game) = stats::simulate(&replay);
+            (stats::placements(&stats), game.turn(), render::draw(&game).to_png().map_err(|e| e.to_string()))
+        };
+        (replay, placements, turn, png)
+    }).await?;
+    let png = png?;
+
+    let mode = match (&replay.teams, replay.usernames.len()) {
+        (Some(_), _) => "Teams",
+        (None, 2) => "1v1",
+        (None, _) => "FFA",
+    };
+    let players = placements.into_iter()
+        .enumerate()
+        .map(|(k, p)| {
+            let color = replay.player_colors.get(p as usize).copied().unwrap_or(p as u32);
+            format!("**{}.** {} {}", k + 1, palette::PLAYER_EMOJI[color as usize % palette::PLAYER_EMOJI.len()], replay.usernames[p as usize])
+        })
+        .collect::<Vec<_>>();
+
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("Replay: {}", replay.id))
+        .url(format!("https://generals.io/replays/{}", replay.id))
+        .description(format!("**Mode**: {}\n**Turns**: {}{}", mode, turn / 2,
+            replay.map_title.as_ref().map(|t| format!("\n**Map**: {}", t)).unwrap_or_default()))
+        .field("Players", players.join("\n"), false)
+        .thumbnail("attachment://board.png")
+        .color(palette::EMBED_GAME);
+    Ok((embed, png))
+}
+
+/// Replies to replay links in channels that have unfurling enabled.
+pub fn on_message(ctx: &Context, msg: &Message) -> Pin<Box<dyn Future<Output=()> + Send>> {
+    let (msg, ctx) = (msg.clone(), ctx.clone());
+
+    Box::pin(async move {
+        if msg.author.bot {
+            return;
+        }
+        let ids = replay_ids(&msg.content);
+        if ids.is_empty() {
+            return;
+        }
+        match DB.get().await.get_unfurl(msg.channel_id.0).await.map_err(|e| e.to_string()) {
+            Ok(true) => {},
+            Ok(false) => return,
+            Err(e) => {
+                eprintln!("unfurl: {}", e);
+                return;
+            }
+        }
+
+        for id in ids {
+            let (embed, png) = match summarize(id).await.map_err(|e| e.to_string()) {
+                Ok(summary) => summary,
+                Err(e) => {
+                    eprintln!("unfurl: replay {}: {}", id, e);
+                    continue;
+                }
+            };
+            let sent = msg.channel_id.send_message(&ctx.http, |m| {
+                m.reference_message(&msg)
+                    .allowed_mentions(|a| a.empty_parse())
+                    .add_file(AttachmentType::Bytes { data: png.into(), filename: "board.png".to_string() })
+                    .set_embed(embed)
+            }).await;
+            if let Err(e) = sent {
+                eprintln!("unfurl: replay {}: {}", id, e);
+            }
+        }
+    })
+}
+
+fn set_unfurl(ctx: &Context, i: &ApplicationCommandInteraction, enabled: bool) -> Pin<Box<dyn Future<Output=crate::Result<()>> + Send>> {
+    let (i, ctx) = (i.clone(), ctx.clone());
+
+    Box::pin(async move {
+        if admin_guild(&ctx, &i, "Unfurl Error").await?.is_none() {
+            return Ok(())
+        }
+
+        DB.get().await.set_unfurl(i.channel_id.0, enabled).await?;
+
+        let mut embed = CreateEmbed::default();
+        embed.title(if enabled { "Unfurling Enabled" } else { "Unfurling Disabled" })
+            .description(format!("Replay links in <#{}> will {}be summarized", i.channel_id, if enabled { "" } else { "no longer " }))
+            .color(palette::EMBED_GAME);
+        embeds::respond(&ctx, &i, embed).await?;
+
+        Ok(())
+    })
+}
+
+lazy_static! {
+    pub static ref COMMAND_UNFURL: Command = Command::group("unfurl", "replay link summaries", vec![
+        Command {
+            name: "enable".into(),
+            description: "summarizes replay links posted in this channel".into(),
+            args: Vec::new(),
+            handler: |ctx, i| set_unfurl(ctx, i, true),
+            subcommands: Vec::new(),
+        },
+        Command {
+            name: "disable".into(),
+            description: "stops summarizing replay links in this channel".into(),
+            args: Vec::new(),
+            handler: |ctx, i| set_unfurl(ctx, i, false),
+            subcommands: Vec::new(),
+        },
+    ]);
+}
@@ -1,5 +1,194 @@
-use sqlx::{Row, Executor};
+use sqlx::{Row, sqlite::SqliteRow};
 
+use crate::{lobbies::Lobby, matchmaking::{QueueEntry, QueueMatch, QueuePlayer}, ratings::{Pool, RatedGame}, registration::Verification, roles::RoleTier, rooms::Room, seasons::{Season, SeasonRating}, stars::{Mode, Stars}, tournament::{Entrant, MatchResult, ScheduledMatch, State, Tournament}};
+
+fn lobby_from_row(x: &SqliteRow) -> Lobby {
+    Lobby {
+        message: x.get::<i64, _>("message") as u64,
+        guild: x.get::<i64, _>("guild") as u64,
+        channel: x.get::<i64, _>("channel") as u64,
+        creator: x.get::<i64, _>("creator") as u64,
+        time: x.get::<i64, _>("time") as u64,
+        code: x.get("code"),
+        map: x.get("map"),
+        speed: x.get::<Option<String>, _>("speed").and_then(|s| s.parse().ok()),
+        pinged: x.get("pinged"),
+        closed: x.get("closed"),
+    }
+}
+
+fn tournament_from_row(x: &SqliteRow) -> Tournament {
+    Tournament {
+        id: x.get::<i64, _>("id") as u64,
+        guild: x.get::<i64, _>("guild") as u64,
+        name: x.get("name"),
+        format: x.get::<String, _>("format").parse().unwrap(),
+        mode: x.get::<String, _>("mode").parse().unwrap(),
+        state: x.get::<String, _>("state").parse().unwrap(),
+        rounds: x.get::<i64, _>("rounds") as u32,
+        creator: x.get::<i64, _>("creator") as u64,
+        created: x.get::<i64, _>("created") as u64,
+    }
+}
+
+fn queue_entry_from_row(x: &SqliteRow) -> QueueEntry {
+    QueueEntry {
+        guild: x.get::<i64, _>("guild") as u64,
+        channel: x.get::<i64, _>("channel") as u64,
+        discord: x.get::<i64, _>("discord") as u64,
+        mode: x.get::<String, _>("mode").parse().unwrap(),
+        stars: x.get("stars"),
+        joined: x.get::<i64, _>("joined") as u64,
+    }
+}
+
+fn season_from_row(x: &SqliteRow) -> Season {
+    Season {
+        guild: x.get::<i64, _>("guild") as u64,
+        number: x.get::<i64, _>("number") as u32,
+        name: x.get("name"),
+        start: x.get::<i64, _>("starts") as u64,
+        end: x.get::<i64, _>("ends") as u64,
+        soft_reset: x.get("soft_reset"),
+        archived: x.get("archived"),
+    }
+}
+
+fn stars_from_row(x: &SqliteRow) -> Stars {
+    Stars {
+        ffa: x.get("ffa"),
+        m1v1: x.get("duel"),
+        m2v2: x.get("m2v2"),
+        ffa_alltime: x.get("ffa_alltime"),
+        m1v1_alltime: x.get("duel_alltime"),
+        m2v2_alltime: x.get("m2v2_alltime"),
+    }
+}
+
+/// The schema, built up in order, each step recorded in `schema_version`
+/// once it succeeds. Never edit or reorder a shipped migration; append a new
+/// one instead. Tables from before versioning use IF NOT EXISTS, since older
+/// databases already have them.
+const MIGRATIONS: &[&[&str]] = &[
+    // 1: registrations, one per discord user and per username (which
+    // generals.io treats case-insensitively), keeping the oldest
+    &[
+        "CREATE TABLE IF NOT EXISTS usernames (discord INT, username TEXT)",
+        "DELETE FROM usernames WHERE rowid NOT IN (SELECT MIN(rowid) FROM usernames GROUP BY discord)",
+        "DELETE FROM usernames WHERE rowid NOT IN (SELECT MIN(rowid) FROM usernames GROUP BY username COLLATE NOCASE)",
+        "CREATE UNIQUE INDEX usernames_discord ON usernames (discord)",
+        "CREATE UNIQUE INDEX usernames_username ON usernames (username COLLATE NOCASE)",
+    ],
+    // 2: star history
+    &[
+        "CREATE TABLE IF NOT EXISTS stars (username TEXT, time INT, ffa REAL, duel REAL, m2v2 REAL, ffa_alltime REAL, duel_alltime REAL, m2v2_alltime REAL)",
+    ],
+    // 3: tier roles
+    &[
+        "CREATE TABLE IF NOT EXISTS role_tiers (guild INT, role INT, mode TEXT, stars REAL, alltime INT)",
+    ],
+    // 4: replay link unfurling
+    &[
+        "CREATE TABLE IF NOT EXISTS unfurl_channels (channel INT)",
+    ],
+    // 5: game announcements
+    &[
+        "CREATE TABLE IF NOT EXISTS announce_channels (guild INT, channel INT)",
+        "CREATE TABLE IF NOT EXISTS watermarks (username TEXT, started INT)",
+        "CREATE TABLE IF NOT EXISTS announced_games (id TEXT, started INT)",
+    ],
+    // 6: custom rooms
+    &[
+        "CREATE TABLE IF NOT EXISTS custom_rooms (guild INT, code TEXT, map TEXT, speed TEXT, creator INT, time INT)",
+    ],
+    // 7: scheduled lobbies
+    &[
+        "CREATE TABLE IF NOT EXISTS lobbies (message INT, guild INT, channel INT, creator INT, time INT, code TEXT, map TEXT, speed TEXT, pinged INT, closed INT)",
+        "CREATE TABLE IF NOT EXISTS rsvps (message INT, discord INT)",
+    ],
+    // 8: tournaments
+    &[
+        "CREATE TABLE IF NOT EXISTS tournaments (id INTEGER PRIMARY KEY AUTOINCREMENT, guild INT, name TEXT, format TEXT, mode TEXT, state TEXT, rounds INT, creator INT, created INT)",
+        "CREATE TABLE IF NOT EXISTS tournament_entrants (tournament INT, discord INT, username TEXT, stars REAL, seed INT)",
+        "CREATE TABLE IF NOT EXISTS tournament_results (tournament INT, match TEXT, winner INT, replay TEXT)",
+        "CREATE TABLE IF NOT EXISTS scheduled_matches (tournament INT, match TEXT, channel INT, time INT, code TEXT, done INT)",
+    ],
+    // 9: server-local ratings
+    &[
+        "CREATE TABLE IF NOT EXISTS rated_games (id TEXT, guild INT, pool TEXT, started INT, ranking TEXT, teams TEXT)",
+    ],
+    // 10: matchmaking queue
+    &[
+        "CREATE TABLE IF NOT EXISTS queue (guild INT, channel INT, discord INT, mode TEXT, stars REAL, joined INT)",
+        "CREATE TABLE IF NOT EXISTS queue_matches (message INT, guild INT, channel INT, mode TEXT, code TEXT, created INT)",
+        "CREATE TABLE IF NOT EXISTS queue_players (message INT, discord INT, stars REAL, joined INT, ready INT)",
+    ],
+    // 11: seasons
+    &[
+        "CREATE TABLE IF NOT EXISTS seasons (guild INT, number INT, name TEXT, starts INT, ends INT, soft_reset INT, archived INT)",
+        "CREATE TABLE IF NOT EXISTS season_stars (guild INT, season INT, username TEXT, ffa REAL, duel REAL, m2v2 REAL, ffa_alltime REAL, duel_alltime REAL, m2v2_alltime REAL)",
+        "CREATE TABLE IF NOT EXISTS season_ratings (guild INT, season INT, pool TEXT, username TEXT, rating REAL, rd REAL, games INT)",
+    ],
+    // 12: registration challenges and relinking cooldowns
+    &[
+        "CREATE TABLE IF NOT EXISTS verifications (discord INT, username TEXT, code TEXT, issued INT)",
+        "CREATE TABLE IF NOT EXISTS link_changes (discord INT, time INT)",
+    ],
+    // 13: earlier names of registered accounts that were renamed on generals.io
+    &[
+        "CREATE TABLE username_history (discord INT, username TEXT, changed INT)",
+    ],
+    // 14: one RSVP per member and lobby
+    &[
+        "DELETE FROM rsvps WHERE rowid NOT IN (SELECT MIN(rowid) FROM rsvps GROUP BY message, discord)",
+        "CREATE UNIQUE INDEX rsvps_message_discord ON rsvps (message, discord)",
+    ],
+    // 15: one entry per member and tournament
+    &[
+        "DELETE FROM tournament_entrants WHERE rowid NOT IN (SELECT MIN(rowid) FROM tournament_entrants GROUP BY tournament, discord)",
+        "CREATE UNIQUE INDEX tournament_entrants_tournament_discord ON tournament_entrants (tournament, discord)",
+    ],
+];
+
+/// Runs every migration newer than the database's schema version.
+async fn migrate(pool: &sqlx::sqlite::SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    sqlx::query("CREATE TABLE IF NOT EXISTS schema_version (version INT)").execute(pool).await?;
+    let version: Option<i64> = sqlx::query("SELECT MAX(version) AS version FROM schema_version")
+        .fetch_one(pool)
+        .await?
+        .get("version");
+    let version = version.unwrap_or(0) as usize;
+
+    for (k, statements) in MIGRATIONS.iter().enumerate().skip(version) {
+        let mut tx = pool.begin().await?;
+        for statement in statements.iter() {
+            // report what deduplication drops, so it can be restored by hand
+            if let Some(condition) = statement.strip_prefix("DELETE FROM ") {
+                let rows = sqlx::query(&format!("SELECT * FROM {}", condition)).fetch_all(&mut tx).await?;
+                for row in rows {
+                    let values = (0..row.len())
+                        .map(|k| row.try_get::<i64, _>(k).map(|v| v.to_string()).or_else(|_| row.try_get::<String, _>(k)).unwrap_or_default())
+                        .collect::<Vec<_>>();
+                    eprintln!("database: schema version {} removes ({})", k + 1, values.join(", "));
+                }
+            }
+            sqlx::query(statement).execute(&mut tx).await?;
+        }
+        sqlx::query("INSERT INTO schema_version VALUES (?)")
+            .bind((k + 1) as i64)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        eprintln!("database: migrated to schema version {}", k + 1);
+    }
+
+    Ok(())
+}
+
+/// Whether `e` came from a UNIQUE constraint.
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(e) if e.message().contains("UNIQUE constraint failed"))
+}
 
 pub struct Database {
     pool: sqlx::sqlite::SqlitePool
@@ -7,7 +196,7 @@ pub struct Database {
 
 impl Database {
     pub async fn new(pool: sqlx::sqlite::SqlitePool) -> Result<Self, Box<dyn std::error::Error>> {
-        sqlx::query("CREATE TABLE IF NOT EXISTS usernames (discord INT, username TEXT) ").execute(&pool).await?;
+        migrate(&pool).await?;
 
         Ok(Self {
             pool
@@ -26,7 +215,7 @@ impl Database {
     }
 
     pub async fn get_discord(&self, username: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
-        let data: Option<i64> = sqlx::query("SELECT discord FROM usernames WHERE username = ?")
+        let data: Option<i64> = sqlx::query("SELECT discord FROM usernames WHERE username = ? COLLATE NOCASE")
             .bind(username)
             .fetch_optional(&self.pool)
             .await?
@@ -35,14 +224,1082 @@ impl Database {
         Ok(data.map(|x| x as u64))
     }
 
-    /// Assumes the entry does not exist for either discord or username.
-    pub async fn add_username(&self, discord: u64, username: &str) -> Result<(), Box<dyn std::error::Error>> { 
-        sqlx::query("INSERT INTO usernames VALUES (?, ?)")
+    /// Returns false if either the discord user or username is already registered.
+    pub async fn add_username(&self, discord: u64, username: &str) -> Result<bool, Box<dyn std::error::Error>> { 
+        let result = sqlx::query("INSERT INTO usernames VALUES (?, ?)")
+            .bind(discord as i64)
+            .bind(username)
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if is_unique_violation(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Points an existing registration at a different username, returning
+    /// false if someone else already has it or `discord` isn't registered.
+    pub async fn set_username(&self, discord: u64, username: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("UPDATE usernames SET username = ? WHERE discord = ?")
+            .bind(username)
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await;
+
+        match result {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) if is_unique_violation(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Follows a generals.io rename in one transaction: updates the
+    /// registration, remembers `old`, and moves everything recorded under
+    /// `old` (star history, watermarks, tournament entries, season archives
+    /// and rated games) over to `new`. Returns false if `new` is registered
+    /// to someone else or `discord` isn't registered.
+    pub async fn rename_username(&self, discord: u64, old: &str, new: &str, time: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("UPDATE usernames SET username = ? WHERE discord = ?")
+            .bind(new)
+            .bind(discord as i64)
+            .execute(&mut tx)
+            .await;
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {},
+            Ok(_) => return Ok(false),
+            Err(e) if is_unique_violation(&e) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        sqlx::query("INSERT INTO username_history VALUES (?, ?, ?)")
+            .bind(discord as i64)
+            .bind(old)
+            .bind(time as i64)
+            .execute(&mut tx)
+            .await?;
+        for statement in [
+            "UPDATE watermarks SET username = ? WHERE username = ? COLLATE NOCASE",
+            "UPDATE stars SET username = ? WHERE username = ? COLLATE NOCASE",
+            "UPDATE tournament_entrants SET username = ? WHERE username = ? COLLATE NOCASE",
+            "UPDATE season_stars SET username = ? WHERE username = ? COLLATE NOCASE",
+            "UPDATE season_ratings SET username = ? WHERE username = ? COLLATE NOCASE",
+        ].iter() {
+            sqlx::query(statement)
+                .bind(new)
+                .bind(old)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        let games = sqlx::query("SELECT rowid, ranking FROM rated_games")
+            .fetch_all(&mut tx)
+            .await?
+            .into_iter()
+            .map(|x| (x.get::<i64, _>("rowid"), x.get::<String, _>("ranking")))
+            .collect::<Vec<_>>();
+        for (rowid, ranking) in games {
+            if !ranking.split('\n').any(|u| u.eq_ignore_ascii_case(old)) {
+                continue;
+            }
+            let ranking = ranking.split('\n').map(|u| if u.eq_ignore_ascii_case(old) { new } else { u }).collect::<Vec<_>>().join("\n");
+            sqlx::query("UPDATE rated_games SET ranking = ? WHERE rowid = ?")
+                .bind(ranking)
+                .bind(rowid)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Earlier names of `discord`'s account, newest first.
+    pub async fn get_previous_usernames(&self, discord: u64) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT username FROM username_history WHERE discord = ? ORDER BY changed DESC")
+            .bind(discord as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| x.get("username"))
+            .collect();
+
+        Ok(data)
+    }
+
+    /// The registered user whose account was most recently called `username`.
+    pub async fn get_discord_by_previous_username(&self, username: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let data: Option<i64> = sqlx::query("SELECT discord FROM username_history WHERE username = ? COLLATE NOCASE ORDER BY changed DESC")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| x.get("discord"));
+
+        Ok(data.map(|x| x as u64))
+    }
+
+    /// Deletes a registration, returning the username it linked.
+    pub async fn remove_username(&self, discord: u64) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let username = self.get_username(discord).await?;
+        sqlx::query("DELETE FROM usernames WHERE discord = ?")
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(username)
+    }
+
+    /// When `discord` last linked or unlinked an account themselves.
+    pub async fn get_link_change(&self, discord: u64) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let data: Option<i64> = sqlx::query("SELECT time FROM link_changes WHERE discord = ?")
+            .bind(discord as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| x.get("time"));
+
+        Ok(data.map(|x| x as u64))
+    }
+
+    pub async fn set_link_change(&self, discord: u64, time: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM link_changes WHERE discord = ?")
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("INSERT INTO link_changes VALUES (?, ?)")
+            .bind(discord as i64)
+            .bind(time as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// All registered users as (discord, username).
+    pub async fn get_users(&self) -> Result<Vec<(u64, String)>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT discord, username FROM usernames")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| (x.get::<i64, _>("discord") as u64, x.get("username")))
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Records a snapshot of `username`'s stars, `time` in seconds since the epoch.
+    pub async fn add_stars(&self, username: &str, time: u64, stars: &Stars) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO stars VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(username)
+            .bind(time as i64)
+            .bind(stars.ffa)
+            .bind(stars.m1v1)
+            .bind(stars.m2v2)
+            .bind(stars.ffa_alltime)
+            .bind(stars.m1v1_alltime)
+            .bind(stars.m2v2_alltime)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Star snapshots of `username`, oldest first.
+    pub async fn get_stars(&self, username: &str) -> Result<Vec<(u64, Stars)>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM stars WHERE username = ? ORDER BY time")
+            .bind(username)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| (x.get::<i64, _>("time") as u64, stars_from_row(&x)))
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Times of the most recent `count` star snapshots, newest first.
+    pub async fn get_snapshot_times(&self, count: u32) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT DISTINCT time FROM stars ORDER BY time DESC LIMIT ?")
+            .bind(count)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| x.get::<i64, _>("time") as u64)
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Every user's stars from the snapshot taken at `time`.
+    pub async fn get_stars_at(&self, time: u64) -> Result<Vec<(String, Stars)>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM stars WHERE time = ?")
+            .bind(time as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| (x.get("username"), stars_from_row(&x)))
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Replaces any existing tier for the same role.
+    pub async fn add_role_tier(&self, guild: u64, tier: &RoleTier) -> Result<(), Box<dyn std::error::Error>> {
+        self.remove_role_tier(guild, tier.role).await?;
+        sqlx::query("INSERT INTO role_tiers VALUES (?, ?, ?, ?, ?)")
+            .bind(guild as i64)
+            .bind(tier.role as i64)
+            .bind(tier.mode.to_string())
+            .bind(tier.stars)
+            .bind(tier.alltime)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether a tier was removed.
+    pub async fn remove_role_tier(&self, guild: u64, role: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("DELETE FROM role_tiers WHERE guild = ? AND role = ?")
+            .bind(guild as i64)
+            .bind(role as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_role_tiers(&self, guild: u64) -> Result<Vec<RoleTier>, Box<dyn std::error::Error>> {
+        let rows = sqlx::query("SELECT role, mode, stars, alltime FROM role_tiers WHERE guild = ? ORDER BY stars")
+            .bind(guild as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut data = Vec::new();
+        for x in rows {
+            data.push(RoleTier {
+                role: x.get::<i64, _>("role") as u64,
+                mode: x.get::<String, _>("mode").parse()?,
+                stars: x.get("stars"),
+                alltime: x.get("alltime"),
+            });
+        }
+        Ok(data)
+    }
+
+    /// Guilds with at least one role tier.
+    pub async fn get_role_tier_guilds(&self) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT DISTINCT guild FROM role_tiers")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| x.get::<i64, _>("guild") as u64)
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Enables or disables replay link unfurling in `channel`.
+    pub async fn set_unfurl(&self, channel: u64, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM unfurl_channels WHERE channel = ?")
+            .bind(channel as i64)
+            .execute(&self.pool)
+            .await?;
+        if enabled {
+            sqlx::query("INSERT INTO unfurl_channels VALUES (?)")
+                .bind(channel as i64)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_unfurl(&self, channel: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT channel FROM unfurl_channels WHERE channel = ?")
+            .bind(channel as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(data.is_some())
+    }
+
+    /// Sets the channel new games are announced in, or stops announcing with `None`.
+    pub async fn set_announce_channel(&self, guild: u64, channel: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM announce_channels WHERE guild = ?")
+            .bind(guild as i64)
+            .execute(&self.pool)
+            .await?;
+        if let Some(channel) = channel {
+            sqlx::query("INSERT INTO announce_channels VALUES (?, ?)")
+                .bind(guild as i64)
+                .bind(channel as i64)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// All announcement channels as (guild, channel).
+    pub async fn get_announce_channels(&self) -> Result<Vec<(u64, u64)>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT guild, channel FROM announce_channels")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| (x.get::<i64, _>("guild") as u64, x.get::<i64, _>("channel") as u64))
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Start time (in milliseconds) of the newest game already seen for `username`.
+    pub async fn get_watermark(&self, username: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let data: Option<i64> = sqlx::query("SELECT started FROM watermarks WHERE username = ?")
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| x.get("started"));
+
+        Ok(data.map(|x| x as u64))
+    }
+
+    pub async fn set_watermark(&self, username: &str, started: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM watermarks WHERE username = ?")
+            .bind(username)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("INSERT INTO watermarks VALUES (?, ?)")
+            .bind(username)
+            .bind(started as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Records that game `id` has been announced. Returns false if it already was.
+    pub async fn mark_announced(&self, id: &str, started: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("INSERT INTO announced_games SELECT ?, ? WHERE NOT EXISTS (SELECT 1 FROM announced_games WHERE id = ?)")
+            .bind(id)
+            .bind(started as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Forgets announced games started (in milliseconds) before `before`.
+    pub async fn prune_announced(&self, before: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM announced_games WHERE started < ?")
+            .bind(before as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn add_room(&self, guild: u64, room: &Room) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO custom_rooms VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(guild as i64)
+            .bind(&room.code)
+            .bind(&room.map)
+            .bind(room.speed.map(|s| s.to_string()))
+            .bind(room.creator as i64)
+            .bind(room.time as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Rooms created in `guild` since `time`, newest first.
+    pub async fn get_rooms(&self, guild: u64, since: u64) -> Result<Vec<Room>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT code, map, speed, creator, time FROM custom_rooms WHERE guild = ? AND time >= ? ORDER BY time DESC")
+            .bind(guild as i64)
+            .bind(since as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| Room {
+                code: x.get("code"),
+                map: x.get("map"),
+                speed: x.get::<Option<String>, _>("speed").and_then(|s| s.parse().ok()),
+                creator: x.get::<i64, _>("creator") as u64,
+                time: x.get::<i64, _>("time") as u64,
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    pub async fn add_lobby(&self, lobby: &Lobby) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO lobbies VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(lobby.message as i64)
+            .bind(lobby.guild as i64)
+            .bind(lobby.channel as i64)
+            .bind(lobby.creator as i64)
+            .bind(lobby.time as i64)
+            .bind(&lobby.code)
+            .bind(&lobby.map)
+            .bind(lobby.speed.map(|s| s.to_string()))
+            .bind(lobby.pinged)
+            .bind(lobby.closed)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The lobby posted as `message`.
+    pub async fn get_lobby(&self, message: u64) -> Result<Option<Lobby>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM lobbies WHERE message = ?")
+            .bind(message as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| lobby_from_row(&x));
+
+        Ok(data)
+    }
+
+    /// Lobbies that have not been closed yet.
+    pub async fn get_open_lobbies(&self) -> Result<Vec<Lobby>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM lobbies WHERE closed = 0")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(lobby_from_row)
+            .collect();
+
+        Ok(data)
+    }
+
+    pub async fn set_lobby_pinged(&self, message: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("UPDATE lobbies SET pinged = 1 WHERE message = ?")
+            .bind(message as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn close_lobby(&self, message: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("UPDATE lobbies SET closed = 1 WHERE message = ?")
+            .bind(message as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adds `discord` to the lobby's RSVPs, returning false if they were already in.
+    pub async fn add_rsvp(&self, message: u64, discord: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("INSERT OR IGNORE INTO rsvps VALUES (?, ?)")
+            .bind(message as i64)
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes `discord` from the lobby's RSVPs, returning false if they weren't in.
+    pub async fn remove_rsvp(&self, message: u64, discord: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("DELETE FROM rsvps WHERE message = ? AND discord = ?")
+            .bind(message as i64)
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Discord IDs that joined the lobby, in order of joining.
+    pub async fn get_rsvps(&self, message: u64) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT discord FROM rsvps WHERE message = ? ORDER BY rowid")
+            .bind(message as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| x.get::<i64, _>("discord") as u64)
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Creates a tournament open for registration, returning its ID.
+    pub async fn add_tournament(&self, tournament: &Tournament) -> Result<u64, Box<dyn std::error::Error>> {
+        let result = sqlx::query("INSERT INTO tournaments (guild, name, format, mode, state, rounds, creator, created) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(tournament.guild as i64)
+            .bind(&tournament.name)
+            .bind(tournament.format.to_string())
+            .bind(tournament.mode.to_string())
+            .bind(tournament.state.to_string())
+            .bind(tournament.rounds as i64)
+            .bind(tournament.creator as i64)
+            .bind(tournament.created as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.last_insert_rowid() as u64)
+    }
+
+    pub async fn get_tournament(&self, id: u64) -> Result<Option<Tournament>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM tournaments WHERE id = ?")
+            .bind(id as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| tournament_from_row(&x));
+
+        Ok(data)
+    }
+
+    /// Tournaments in `guild`, newest first.
+    pub async fn get_tournaments(&self, guild: u64) -> Result<Vec<Tournament>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM tournaments WHERE guild = ? ORDER BY id DESC")
+            .bind(guild as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(tournament_from_row)
+            .collect();
+
+        Ok(data)
+    }
+
+    pub async fn set_tournament_state(&self, id: u64, state: State) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("UPDATE tournaments SET state = ? WHERE id = ?")
+            .bind(state.to_string())
+            .bind(id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_tournament_rounds(&self, id: u64, rounds: u32) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("UPDATE tournaments SET rounds = ? WHERE id = ?")
+            .bind(rounds as i64)
+            .bind(id as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adds an unseeded entrant, returning false if they already entered.
+    pub async fn add_entrant(&self, tournament: u64, discord: u64, username: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("INSERT OR IGNORE INTO tournament_entrants VALUES (?, ?, ?, 0, NULL)")
+            .bind(tournament as i64)
             .bind(discord as i64)
             .bind(username)
             .execute(&self.pool)
             .await?;
 
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_entrant(&self, tournament: u64, discord: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("DELETE FROM tournament_entrants WHERE tournament = ? AND discord = ?")
+            .bind(tournament as i64)
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Entrants in seed order, or registration order before seeding.
+    pub async fn get_entrants(&self, tournament: u64) -> Result<Vec<Entrant>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT discord, username, stars FROM tournament_entrants WHERE tournament = ? ORDER BY seed, rowid")
+            .bind(tournament as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| Entrant {
+                discord: x.get::<i64, _>("discord") as u64,
+                username: x.get("username"),
+                stars: x.get("stars"),
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Stores the stars and seeds of `entrants`, given in seed order.
+    pub async fn set_seeds(&self, tournament: u64, entrants: &[Entrant]) -> Result<(), Box<dyn std::error::Error>> {
+        for (seed, entrant) in entrants.iter().enumerate() {
+            sqlx::query("UPDATE tournament_entrants SET stars = ?, seed = ? WHERE tournament = ? AND discord = ?")
+                .bind(entrant.stars)
+                .bind(seed as i64)
+                .bind(tournament as i64)
+                .bind(entrant.discord as i64)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn add_result(&self, tournament: u64, result: &MatchResult) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO tournament_results VALUES (?, ?, ?, ?)")
+            .bind(tournament as i64)
+            .bind(&result.id)
+            .bind(result.winner as i64)
+            .bind(&result.replay)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_results(&self, tournament: u64) -> Result<Vec<MatchResult>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT match, winner, replay FROM tournament_results WHERE tournament = ? ORDER BY rowid")
+            .bind(tournament as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| MatchResult {
+                id: x.get("match"),
+                winner: x.get::<i64, _>("winner") as u64,
+                replay: x.get("replay"),
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Schedules a match, replacing any earlier schedule for it.
+    pub async fn add_scheduled_match(&self, scheduled: &ScheduledMatch) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM scheduled_matches WHERE tournament = ? AND match = ?")
+            .bind(scheduled.tournament as i64)
+            .bind(&scheduled.id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("INSERT INTO scheduled_matches VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(scheduled.tournament as i64)
+            .bind(&scheduled.id)
+            .bind(scheduled.channel as i64)
+            .bind(scheduled.time as i64)
+            .bind(&scheduled.code)
+            .bind(scheduled.done)
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Scheduled matches still waiting for a result.
+    pub async fn get_pending_scheduled_matches(&self) -> Result<Vec<ScheduledMatch>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM scheduled_matches WHERE done = 0 ORDER BY time")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| ScheduledMatch {
+                tournament: x.get::<i64, _>("tournament") as u64,
+                id: x.get("match"),
+                channel: x.get::<i64, _>("channel") as u64,
+                time: x.get::<i64, _>("time") as u64,
+                code: x.get("code"),
+                done: x.get("done"),
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    pub async fn set_scheduled_match_done(&self, tournament: u64, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("UPDATE scheduled_matches SET done = 1 WHERE tournament = ? AND match = ?")
+            .bind(tournament as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn has_rated_game(&self, guild: u64, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT id FROM rated_games WHERE guild = ? AND id = ?")
+            .bind(guild as i64)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(data.is_some())
+    }
+
+    pub async fn add_rated_game(&self, game: &RatedGame) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO rated_games VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(&game.id)
+            .bind(game.guild as i64)
+            .bind(game.pool.to_string())
+            .bind(game.started as i64)
+            .bind(game.ranking.join("\n"))
+            .bind(game.teams.as_ref().map(|t| t.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(",")))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Rated games in `guild` and `pool`, oldest first.
+    pub async fn get_rated_games(&self, guild: u64, pool: Pool) -> Result<Vec<RatedGame>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM rated_games WHERE guild = ? AND pool = ? ORDER BY started")
+            .bind(guild as i64)
+            .bind(pool.to_string())
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| RatedGame {
+                id: x.get("id"),
+                guild,
+                pool,
+                started: x.get::<i64, _>("started") as u64,
+                ranking: x.get::<String, _>("ranking").split('\n').map(|u| u.to_string()).collect(),
+                teams: x.get::<Option<String>, _>("teams").map(|t| t.split(',').filter_map(|t| t.parse().ok()).collect()),
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Queues a player, replacing any place they already had in this guild.
+    pub async fn add_queue_entry(&self, entry: &QueueEntry) -> Result<(), Box<dyn std::error::Error>> {
+        self.remove_queue_entry(entry.guild, entry.discord).await?;
+        sqlx::query("INSERT INTO queue VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(entry.guild as i64)
+            .bind(entry.channel as i64)
+            .bind(entry.discord as i64)
+            .bind(entry.mode.to_string())
+            .bind(entry.stars)
+            .bind(entry.joined as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_queue_entry(&self, guild: u64, discord: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("DELETE FROM queue WHERE guild = ? AND discord = ?")
+            .bind(guild as i64)
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes `players` from the `mode` queue in `guild`, all or nothing.
+    /// Returns false, removing no one, if any of them already left the queue.
+    pub async fn claim_queue_entries(&self, guild: u64, mode: Mode, players: &[u64]) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+        for discord in players {
+            let result = sqlx::query("DELETE FROM queue WHERE guild = ? AND discord = ? AND mode = ?")
+                .bind(guild as i64)
+                .bind(*discord as i64)
+                .bind(mode.to_string())
+                .execute(&mut tx)
+                .await?;
+            if result.rows_affected() == 0 {
+                tx.rollback().await?;
+                return Ok(false);
+            }
+        }
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Players queued for `mode` in `guild`, longest waiting first.
+    pub async fn get_queue(&self, guild: u64, mode: Mode) -> Result<Vec<QueueEntry>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM queue WHERE guild = ? AND mode = ? ORDER BY joined")
+            .bind(guild as i64)
+            .bind(mode.to_string())
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(queue_entry_from_row)
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Removes and returns every player who joined before `time`.
+    pub async fn remove_stale_queue_entries(&self, time: u64) -> Result<Vec<QueueEntry>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM queue WHERE joined < ?")
+            .bind(time as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(queue_entry_from_row)
+            .collect();
+        sqlx::query("DELETE FROM queue WHERE joined < ?")
+            .bind(time as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(data)
+    }
+
+    pub async fn add_queue_match(&self, m: &QueueMatch, players: &[QueuePlayer]) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO queue_matches VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(m.message as i64)
+            .bind(m.guild as i64)
+            .bind(m.channel as i64)
+            .bind(m.mode.to_string())
+            .bind(&m.code)
+            .bind(m.created as i64)
+            .execute(&self.pool)
+            .await?;
+        for p in players {
+            sqlx::query("INSERT INTO queue_players VALUES (?, ?, ?, ?, ?)")
+                .bind(m.message as i64)
+                .bind(p.discord as i64)
+                .bind(p.stars)
+                .bind(p.joined as i64)
+                .bind(p.ready)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_queue_match(&self, message: u64) -> Result<Option<QueueMatch>, Box<dyn std::error::Error>> {
+        Ok(self.get_queue_matches().await?.into_iter().find(|m| m.message == message))
+    }
+
+    /// Matches still waiting for their ready check.
+    pub async fn get_queue_matches(&self) -> Result<Vec<QueueMatch>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM queue_matches")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| QueueMatch {
+                message: x.get::<i64, _>("message") as u64,
+                guild: x.get::<i64, _>("guild") as u64,
+                channel: x.get::<i64, _>("channel") as u64,
+                mode: x.get::<String, _>("mode").parse().unwrap(),
+                code: x.get("code"),
+                created: x.get::<i64, _>("created") as u64,
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    pub async fn remove_queue_match(&self, message: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM queue_matches WHERE message = ?")
+            .bind(message as i64)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM queue_players WHERE message = ?")
+            .bind(message as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks `discord` ready, returning false if they aren't in the match.
+    pub async fn set_queue_ready(&self, message: u64, discord: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let result = sqlx::query("UPDATE queue_players SET ready = 1 WHERE message = ? AND discord = ?")
+            .bind(message as i64)
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Whether `discord` is in a popped match that is still waiting for its ready check.
+    pub async fn in_queue_match(&self, discord: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT 1 FROM queue_players WHERE discord = ?")
+            .bind(discord as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(data.is_some())
+    }
+
+    pub async fn get_queue_players(&self, message: u64) -> Result<Vec<QueuePlayer>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM queue_players WHERE message = ? ORDER BY rowid")
+            .bind(message as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| QueuePlayer {
+                discord: x.get::<i64, _>("discord") as u64,
+                stars: x.get("stars"),
+                joined: x.get::<i64, _>("joined") as u64,
+                ready: x.get("ready"),
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    pub async fn add_season(&self, season: &Season) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("INSERT INTO seasons VALUES (?, ?, ?, ?, ?, ?, ?)")
+            .bind(season.guild as i64)
+            .bind(season.number)
+            .bind(&season.name)
+            .bind(season.start as i64)
+            .bind(season.end as i64)
+            .bind(season.soft_reset)
+            .bind(season.archived)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Seasons of `guild`, oldest first.
+    pub async fn get_seasons(&self, guild: u64) -> Result<Vec<Season>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM seasons WHERE guild = ? ORDER BY number")
+            .bind(guild as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(season_from_row)
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Seasons in every guild that ended by `time` but aren't archived yet.
+    pub async fn get_ended_seasons(&self, time: u64) -> Result<Vec<Season>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM seasons WHERE archived = 0 AND ends <= ?")
+            .bind(time as i64)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(season_from_row)
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Replaces `season`'s archive with `stars` and the standings of each
+    /// rating pool, and marks it archived, all in one transaction so an
+    /// interrupted or repeated archive never leaves duplicate rows.
+    pub async fn archive_season(&self, guild: u64, season: u32, stars: &[(String, Stars)], ratings: &[(Pool, Vec<SeasonRating>)]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+        for statement in ["DELETE FROM season_stars WHERE guild = ? AND season = ?", "DELETE FROM season_ratings WHERE guild = ? AND season = ?"].iter() {
+            sqlx::query(statement)
+                .bind(guild as i64)
+                .bind(season)
+                .execute(&mut tx)
+                .await?;
+        }
+        for (username, s) in stars {
+            sqlx::query("INSERT INTO season_stars VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
+                .bind(guild as i64)
+                .bind(season)
+                .bind(username)
+                .bind(s.ffa)
+                .bind(s.m1v1)
+                .bind(s.m2v2)
+                .bind(s.ffa_alltime)
+                .bind(s.m1v1_alltime)
+                .bind(s.m2v2_alltime)
+                .execute(&mut tx)
+                .await?;
+        }
+        for (pool, standings) in ratings {
+            for r in standings {
+                sqlx::query("INSERT INTO season_ratings VALUES (?, ?, ?, ?, ?, ?, ?)")
+                    .bind(guild as i64)
+                    .bind(season)
+                    .bind(pool.to_string())
+                    .bind(&r.username)
+                    .bind(r.rating)
+                    .bind(r.rd)
+                    .bind(r.games)
+                    .execute(&mut tx)
+                    .await?;
+            }
+        }
+        sqlx::query("UPDATE seasons SET archived = 1 WHERE guild = ? AND number = ?")
+            .bind(guild as i64)
+            .bind(season)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Stars of members when `season` ended.
+    pub async fn get_season_stars(&self, guild: u64, season: u32) -> Result<Vec<(String, Stars)>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM season_stars WHERE guild = ? AND season = ?")
+            .bind(guild as i64)
+            .bind(season)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|x| (x.get("username"), stars_from_row(x)))
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Final ratings of `season`, best first.
+    pub async fn get_season_ratings(&self, guild: u64, season: u32, pool: Pool) -> Result<Vec<SeasonRating>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM season_ratings WHERE guild = ? AND season = ? AND pool = ? ORDER BY rating DESC")
+            .bind(guild as i64)
+            .bind(season)
+            .bind(pool.to_string())
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| SeasonRating {
+                username: x.get("username"),
+                rating: x.get("rating"),
+                rd: x.get("rd"),
+                games: x.get::<i64, _>("games") as u32,
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Stores a registration challenge, replacing any earlier one by the same user.
+    pub async fn set_verification(&self, v: &Verification) -> Result<(), Box<dyn std::error::Error>> {
+        self.remove_verification(v.discord).await?;
+        sqlx::query("INSERT INTO verifications VALUES (?, ?, ?, ?)")
+            .bind(v.discord as i64)
+            .bind(&v.username)
+            .bind(&v.code)
+            .bind(v.issued as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_verification(&self, discord: u64) -> Result<Option<Verification>, Box<dyn std::error::Error>> {
+        let data = sqlx::query("SELECT * FROM verifications WHERE discord = ?")
+            .bind(discord as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| Verification {
+                discord,
+                username: x.get("username"),
+                code: x.get("code"),
+                issued: x.get::<i64, _>("issued") as u64,
+            });
+
+        Ok(data)
+    }
+
+    pub async fn remove_verification(&self, discord: u64) -> Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("DELETE FROM verifications WHERE discord = ?")
+            .bind(discord as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Glicko-2 ratings, following Glickman's "Example of the Glicko-2 system"
+
+use std::f64::consts::PI;
+
+/// Converts between the Glicko and Glicko-2 scales.
+const SCALE: f64 = 173.7178;
+/// Constrains volatility changes.
+const TAU: f64 = 0.5;
+const EPSILON: f64 = 0.000001;
+/// Deviation added by a soft reset.
+const SOFT_RESET_RD: f64 = 150.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rating {
+    pub rating: f64,
+    /// Rating deviation.
+    pub rd: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating { rating: 1500.0, rd: 350.0, volatility: 0.06 }
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+impl Rating {
+    /// Rating after one period with `results` as (opponent, score) where
+    /// score is 1 for a win and 0 for a loss.
+    pub fn update(&self, results: &[(Rating, f64)]) -> Rating {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.rd / SCALE;
+        if results.is_empty() {
+            let phi = (phi * phi + self.volatility * self.volatility).sqrt();
+            return Rating { rd: phi * SCALE, ..*self };
+        }
+
+        let terms = results.iter().map(|(opponent, score)| {
+            let mu_j = (opponent.rating - 1500.0) / SCALE;
+            let g = g(opponent.rd / SCALE);
+            let e = 1.0 / (1.0 + (-g * (mu - mu_j)).exp());
+            (g, e, *score)
+        }).collect::<Vec<_>>();
+        let v = 1.0 / terms.iter().map(|(g, e, _)| g * g * e * (1.0 - e)).sum::<f64>();
+        let improvement = terms.iter().map(|(g, e, s)| g * (s - e)).sum::<f64>();
+        let delta = v * improvement;
+
+        // new volatility by the Illinois algorithm
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * (phi * phi + v + ex).powi(2)) - (x - a) / (TAU * TAU)
+        };
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+        let (mut f_a, mut f_b) = (f(big_a), f(big_b));
+        while (big_b - big_a).abs() > EPSILON {
+            let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(big_c);
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+            big_b = big_c;
+            f_b = f_c;
+        }
+        let volatility = (big_a / 2.0).exp();
+
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let mu = mu + phi * phi * improvement;
+        Rating { rating: mu * SCALE + 1500.0, rd: phi * SCALE, volatility }
+    }
+
+    /// Pulls the rating halfway back to the default and widens its deviation,
+    /// for the start of a new season.
+    pub fn soft_reset(&self) -> Rating {
+        let default = Rating::default();
+        Rating {
+            rating: (self.rating + default.rating) / 2.0,
+            rd: (self.rd * self.rd + SOFT_RESET_RD * SOFT_RESET_RD).sqrt().min(default.rd),
+            volatility: self.volatility,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rating;
+
+    #[test]
+    fn glickman_example() {
+        let player = Rating { rating: 1500.0, rd: 200.0, volatility: 0.06 };
+        let opponent = |rating, rd| Rating { rating, rd, volatility: 0.06 };
+        let updated = player.update(&[(opponent(1400.0, 30.0), 1.0), (opponent(1550.0, 100.0), 0.0), (opponent(1700.0, 300.0), 0.0)]);
+        assert!((updated.rating - 1464.06).abs() < 0.01, "rating {}", updated.rating);
+        assert!((updated.rd - 151.52).abs() < 0.01, "rd {}", updated.rd);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001, "volatility {}", updated.volatility);
+    }
+
+    #[test]
+    fn idle_period_widens_deviation() {
+        let player = Rating { rating: 1500.0, rd: 200.0, volatility: 0.06 };
+        let updated = player.update(&[]);
+        assert_eq!(updated.rating, 1500.0);
+        assert!((updated.rd - 200.27).abs() < 0.01, "rd {}", updated.rd);
+    }
+}
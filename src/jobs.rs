@@ -0,0 +1,243 @@
+//! Background jobs
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serenity::{http::Http, model::id::{ChannelId, GuildId}};
+
+use crate::{commands::{announce, lobby, queue, tournament}, replay::metadata::{self, Metadata}, renames, roles, seasons, stars, DB};
+
+const STAR_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const ROLE_SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const LOBBY_INTERVAL: Duration = Duration::from_secs(60);
+const VERIFY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const QUEUE_INTERVAL: Duration = Duration::from_secs(30);
+const SEASON_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Delay between generals.io requests, to stay clear of rate limits.
+const REQUEST_DELAY: Duration = Duration::from_secs(1);
+/// Longest delay between requests after repeated failures.
+const MAX_REQUEST_DELAY: Duration = Duration::from_secs(60);
+/// Games fetched per user and poll; more than this between polls are skipped.
+const ANNOUNCE_PAGE: usize = 10;
+/// How long announced game IDs are kept, so a game between registered users is only posted once.
+const ANNOUNCED_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Records the stars of every registered user into the `stars` table.
+pub async fn snapshot_stars() {
+    let mut interval = tokio::time::interval(STAR_SNAPSHOT_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let users = match DB.get().await.get_users().await {
+            Ok(users) => users,
+            Err(e) => {
+                eprintln!("star snapshot: {}", e);
+                continue;
+            }
+        };
+
+        let time = crate::unix_time();
+        for (_, username) in users {
+            let result = match stars::fetch(&username).await.map_err(|e| e.to_string()) {
+                Ok(stars) => DB.get().await.add_stars(&username, time, &stars).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("star snapshot for {}: {}", username, e);
+            }
+            tokio::time::sleep(REQUEST_DELAY).await;
+        }
+    }
+}
+
+/// Syncs star tier roles in every guild that has them configured.
+pub async fn sync_roles(http: Arc<Http>) {
+    let mut interval = tokio::time::interval(ROLE_SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let guilds = match DB.get().await.get_role_tier_guilds().await {
+            Ok(guilds) => guilds,
+            Err(e) => {
+                eprintln!("role sync: {}", e);
+                continue;
+            }
+        };
+
+        for guild in guilds {
+            if let Err(e) = roles::sync_guild(&http, GuildId(guild)).await.map_err(|e| e.to_string()) {
+                eprintln!("role sync for {}: {}", guild, e);
+            }
+        }
+    }
+}
+
+/// Posts newly finished games of registered users to each guild's
+/// announcement channel. A user's first poll only sets their watermark,
+/// so old games are never announced.
+pub async fn announce_games(http: Arc<Http>) {
+    let mut interval = tokio::time::interval(ANNOUNCE_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut delay = REQUEST_DELAY;
+    loop {
+        interval.tick().await;
+
+        let db = DB.get().await;
+        let cutoff = crate::unix_time().saturating_sub(ANNOUNCED_RETENTION.as_secs()) * 1000;
+        if let Err(e) = db.prune_announced(cutoff).await.map_err(|e| e.to_string()) {
+            eprintln!("announcements: {}", e);
+        }
+        let (users, channels) = match (db.get_users().await.map_err(|e| e.to_string()), db.get_announce_channels().await.map_err(|e| e.to_string())) {
+            (Ok(users), Ok(channels)) => (users, channels),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("announcements: {}", e);
+                continue;
+            }
+        };
+        if channels.is_empty() {
+            continue;
+        }
+        let discord_of = users.iter().map(|(d, u)| (u.to_lowercase(), *d)).collect::<HashMap<_, _>>();
+
+        for (_, username) in &users {
+            tokio::time::sleep(delay).await;
+            let games = match metadata::fetch(username, 0, ANNOUNCE_PAGE).await.map_err(|e| e.to_string()) {
+                Ok(games) => {
+                    delay = REQUEST_DELAY;
+                    games
+                },
+                Err(e) => {
+                    // most likely rate limited, so slow down until requests succeed again
+                    delay = (delay * 2).min(MAX_REQUEST_DELAY);
+                    eprintln!("announcements for {}: {}", username, e);
+                    continue;
+                }
+            };
+
+            // after a rename the watermark lives under the new name
+            let username = match renames::detect(username, &games).await.map_err(|e| e.to_string()) {
+                Ok(Some((_, current))) => current,
+                Ok(None) => username.clone(),
+                Err(e) => {
+                    eprintln!("renames: {}", e);
+                    username.clone()
+                }
+            };
+
+            let watermark = match db.get_watermark(&username).await.map_err(|e| e.to_string()) {
+                Ok(watermark) => watermark,
+                Err(e) => {
+                    eprintln!("announcements for {}: {}", username, e);
+                    continue;
+                }
+            };
+            let newest = match games.first() {
+                Some(game) => game.started,
+                None => continue,
+            };
+            if watermark.is_some_and(|w| w >= newest) {
+                continue;
+            }
+            if let Err(e) = db.set_watermark(&username, newest).await.map_err(|e| e.to_string()) {
+                eprintln!("announcements for {}: {}", username, e);
+                continue;
+            }
+            let watermark = match watermark {
+                Some(watermark) => watermark,
+                None => continue,
+            };
+
+            for game in games.iter().rev().filter(|g| g.started > watermark) {
+                match db.mark_announced(&game.id, game.started).await.map_err(|e| e.to_string()) {
+                    Ok(true) => announce_game(&http, game, &channels, &discord_of).await,
+                    Ok(false) => {},
+                    Err(e) => eprintln!("announcements for {}: {}", username, e),
+                }
+            }
+        }
+    }
+}
+
+/// Posts `game` in every channel whose guild has one of its registered players.
+async fn announce_game(http: &Http, game: &Metadata, channels: &[(u64, u64)], discord_of: &HashMap<String, u64>) {
+    let players = game.ranking.iter()
+        .filter_map(|p| discord_of.get(&p.name.to_lowercase()).or_else(|| discord_of.get(&p.current_name.to_lowercase())))
+        .copied()
+        .collect::<Vec<_>>();
+
+    for &(guild, channel) in channels {
+        let mut present = Vec::new();
+        for &discord in &players {
+            if http.get_member(guild, discord).await.is_ok() {
+                present.push(discord);
+            }
+        }
+        if present.is_empty() {
+            continue;
+        }
+
+        let embed = announce::result_card(game, &present, discord_of);
+        if let Err(e) = ChannelId(channel).send_message(http, |m| m.set_embed(embed)).await {
+            eprintln!("announcements in {}: {}", channel, e);
+        }
+    }
+}
+
+/// Pings and closes scheduled lobbies.
+pub async fn run_lobbies(http: Arc<Http>) {
+    let mut interval = tokio::time::interval(LOBBY_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = lobby::update_lobbies(&http).await.map_err(|e| e.to_string()) {
+            eprintln!("lobbies: {}", e);
+        }
+    }
+}
+
+/// Records results of scheduled tournament matches from replays.
+pub async fn verify_matches(http: Arc<Http>) {
+    let mut interval = tokio::time::interval(VERIFY_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = tournament::verify_matches(&http).await.map_err(|e| e.to_string()) {
+            eprintln!("match verification: {}", e);
+        }
+    }
+}
+
+/// Expires queue entries and ready checks.
+pub async fn run_queue(http: Arc<Http>) {
+    let mut interval = tokio::time::interval(QUEUE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = queue::expire(&http).await.map_err(|e| e.to_string()) {
+            eprintln!("queue: {}", e);
+        }
+    }
+}
+
+/// Archives seasons once they end.
+pub async fn archive_seasons(http: Arc<Http>) {
+    let mut interval = tokio::time::interval(SEASON_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let ended = DB.get().await.get_ended_seasons(crate::unix_time()).await.map_err(|e| e.to_string());
+        let ended = match ended {
+            Ok(ended) => ended,
+            Err(e) => {
+                eprintln!("seasons: {}", e);
+                continue;
+            }
+        };
+        for season in ended {
+            if let Err(e) = seasons::archive(&http, &season).await.map_err(|e| e.to_string()) {
+                eprintln!("seasons: {}", e);
+            }
+        }
+    }
+}
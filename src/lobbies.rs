@@ -0,0 +1,24 @@
+//! Scheduled custom games
+
+use crate::rooms::{self, Speed};
+
+/// A scheduled custom game, identified by the message it was posted as.
+pub struct Lobby {
+    pub message: u64,
+    pub guild: u64,
+    pub channel: u64,
+    pub creator: u64,
+    /// Start time in seconds since the epoch.
+    pub time: u64,
+    pub code: String,
+    pub map: Option<String>,
+    pub speed: Option<Speed>,
+    pub pinged: bool,
+    pub closed: bool,
+}
+
+impl Lobby {
+    pub fn url(&self) -> String {
+        rooms::room_url(&self.code, self.map.as_deref(), self.speed)
+    }
+}
@@ -3,19 +3,43 @@ extern crate lazy_static;
 
 extern crate serenity;
 
+mod balance;
+mod chart;
 mod commands;
 mod database;
+mod glicko;
+mod jobs;
+mod lobbies;
+mod matchmaking;
 mod palette;
-
-use std::{env, any::TypeId, sync::Mutex, ops::Deref, pin::Pin, f32::consts::E};
+mod ratings;
+mod registration;
+mod renames;
+mod replay;
+mod roles;
+mod rooms;
+mod seasons;
+mod stars;
+mod tournament;
+
+use std::{env, any::TypeId, pin::Pin};
 use async_once::AsyncOnce;
 use futures::Future;
-use serde::{Deserialize, Serialize};
-use serenity::{model::{guild::Member, interactions::application_command::ApplicationCommandInteraction, id::UserId}, client::Context, builder::CreateEmbed, prelude::RwLock};
+use serenity::{model::{guild::Member, interactions::application_command::ApplicationCommandInteraction, id::{GuildId, UserId}}, client::{bridge::gateway::GatewayIntents, Context}, builder::CreateEmbed, http::Http};
 use sqlx::sqlite::SqliteConnectOptions;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Number of recent games per mode summarized on profiles.
+const RECENT_GAMES: usize = 20;
+/// Most recent games searched for each mode's form.
+const FORM_SCAN: usize = 100;
+
 /*
 struct Handler;
 
@@ -65,33 +89,28 @@ lazy_static! {
 }
 
 async fn create_user_embed(username: &str, discord: Option<UserId>) -> Result<CreateEmbed> {
-    #[derive(Serialize, Deserialize)]
-    struct Stars {
-        ffa: Option<String>,
-        #[serde(rename = "duel")]
-        m1v1: Option<String>,
-        #[serde(rename = "2v2")]
-        m2v2: Option<String>,
-
-        #[serde(rename = "ffa-alltime")]
-        ffa_alltime: Option<String>,
-        #[serde(rename = "duel-alltime")]
-        m1v1_alltime: Option<String>,
-        #[serde(rename = "2v2-alltime")]
-        m2v2_alltime: Option<String>,
-    }
-
-    #[derive(Serialize, Deserialize)]
-    struct StarsAndRanks {
-        stars: Stars,
-    }
+    // recent form is optional, so the profile still shows if replays fail to load
+    let games = match replay::metadata::fetch(username, 0, FORM_SCAN).await.map_err(|e| e.to_string()) {
+        Ok(games) => games,
+        Err(e) => {
+            eprintln!("profile {}: {}", username, e);
+            Vec::new()
+        }
+    };
+    // the listing also shows whether the account was renamed since
+    let username = match renames::detect(username, &games).await.map_err(|e| e.to_string()) {
+        Ok(Some((_, current))) => current,
+        Ok(None) => username.to_string(),
+        Err(e) => {
+            eprintln!("profile {}: {}", username, e);
+            username.to_string()
+        }
+    };
+    let username = username.as_str();
 
-    let resp: StarsAndRanks = reqwest::get(&format!("https://generals.io/api/starsAndRanks?u={}", urlencoding::encode(&username)))
-        .await?
-        .json()
-        .await?;
+    let stars = stars::fetch(username).await?;
 
-    let mut iq: f64 = resp.stars.m1v1_alltime.unwrap_or("0.0".to_string()).parse()?;
+    let mut iq: f64 = stars.m1v1_alltime.unwrap_or(0.0);
     iq = (iq - 65.0) / 8.0;
     iq = iq * 15.0 + 100.0;
     let mut iq = iq as i64;
@@ -100,27 +119,81 @@ async fn create_user_embed(username: &str, discord: Option<UserId>) -> Result<Cr
     iq -= 2 * username.chars().filter(|&x| x == 'c' || x == 'C').collect::<Vec<_>>().len() as i64;
     iq -= 3 * username.chars().filter(|&x| x == 'd' || x == 'D').collect::<Vec<_>>().len() as i64;
     iq -= 5 * username.chars().filter(|&x| x == 'f' || x == 'F').collect::<Vec<_>>().len() as i64;
-    if iq > 160 { iq = 160; }
-    if iq < 0 { iq = 0; }
+    iq = iq.clamp(0, 160);
+
+    let previous = match discord {
+        Some(discord) => DB.get().await.get_previous_usernames(discord.0).await?,
+        None => Vec::new(),
+    };
+    let form = replay::metadata::form(&games, username, RECENT_GAMES);
+    let streak = replay::metadata::streak(&games, username);
 
     let mut embed = CreateEmbed::default();
     embed.title(format!("Profile: {}", &username));
+    embed.url(format!("https://generals.io/profiles/{}", urlencoding::encode(username)));
     embed.description(
         format!(concat!(
+            "{}",
             "{}",
             "**FFA Stars**: {}\n",
             "**1v1 Stars**: {}\n",
             "**Estimated IQ**: {}"
         ), 
             if let Some(discord) = discord { format!("**Discord**: <@{}>\n", discord.0) } else { "".to_string() },
-            resp.stars.ffa.map(|x| x.parse::<f64>()).transpose()?.map(|x| format!("{:.2}", x)).unwrap_or("---".to_string()),
-            resp.stars.m1v1.map(|x| x.parse::<f64>()).transpose()?.map(|x| format!("{:.2}", x)).unwrap_or("---".to_string()),
+            if previous.is_empty() { "".to_string() } else { format!("**Formerly**: {}\n", previous.join(", ")) },
+            stars.ffa.map(|x| format!("{:.2}", x)).unwrap_or("---".to_string()),
+            stars.m1v1.map(|x| format!("{:.2}", x)).unwrap_or("---".to_string()),
             iq)
     );
+    if !form.is_empty() {
+        let mut lines = form.iter()
+            .map(|(mode, f)| format!("**{}**: {}W {}L, avg. place {:.1}", mode, f.wins, f.losses, f.average_placement()))
+            .collect::<Vec<_>>();
+        lines.push(match streak {
+            s if s >= 3 => format!("**Streak**: 🔥 {} wins", s),
+            s if s > 0 => format!("**Streak**: {} win{}", s, if s == 1 { "" } else { "s" }),
+            s if s <= -3 => format!("**Streak**: 🧊 {} losses", -s),
+            s => format!("**Streak**: {} loss{}", -s, if s == -1 { "" } else { "es" }),
+        });
+        lines.push(format!("[Full history](https://generals.io/profiles/{})", urlencoding::encode(username)));
+        embed.field(format!("Recent Form (last {} games per mode)", RECENT_GAMES), lines.join("\n"), false);
+    }
     embed.color(palette::EMBED_GAME);
     Ok(embed)
 }
 
+/// Resolves a generals.io username, or a Discord mention or ID of a registered user.
+async fn resolve_username(input: &str) -> Result<Option<String>> {
+    let id = input.trim_start_matches("<@").trim_start_matches('!').trim_end_matches('>');
+    if let Ok(id) = id.parse::<u64>() {
+        if let Some(username) = DB.get().await.get_username(id).await? {
+            return Ok(Some(username));
+        }
+    }
+    if input.starts_with("<@") {
+        return Ok(None);
+    }
+    Ok(Some(input.to_string()))
+}
+
+/// Every member of `guild`. Requires the Server Members intent.
+async fn guild_members(http: impl AsRef<Http>, guild: GuildId) -> Result<Vec<Member>> {
+    let mut members = Vec::new();
+    loop {
+        let page = guild.members(&http, Some(1000), members.last().map(|m: &Member| m.user.id)).await?;
+        let len = page.len();
+        members.extend(page);
+        if len < 1000 {
+            return Ok(members);
+        }
+    }
+}
+
+/// Whether the user who ran `i` can manage the server.
+fn is_admin(i: &ApplicationCommandInteraction) -> bool {
+    i.member.as_ref().and_then(|m| m.permissions).is_some_and(|p| p.administrator() || p.manage_guild())
+}
+
 fn handle_user(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=Result<()>> + Send>> {
     let i = i.clone();
     let ctx = ctx.clone();
@@ -129,8 +202,8 @@ fn handle_user(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn
         let mut user = i.user.id;
 
         for opt in &i.data.options {
-            if opt.name == "mention" && opt.value.is_some() {
-                user = UserId(opt.value.as_ref().unwrap().as_str().unwrap().parse()?);
+            if let (true, Some(value)) = (opt.name == "mention", &opt.value) {
+                user = UserId(value.as_str().unwrap().parse()?);
             }
         }
 
@@ -138,8 +211,10 @@ fn handle_user(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn
 
         let username = db.get_username(user.0).await?;
         if let Some(username) = username {
-            let embed = create_user_embed(&username, Some(user)).await?;
-            embeds::respond(&ctx, &i, embed).await?;
+            // the profile takes several requests, so acknowledge first
+            embeds::defer(&ctx, &i).await?;
+            let embed = create_user_embed(&username, Some(user)).await.map_err(|e| e.to_string())?;
+            embeds::followup(&ctx, &i, embed, None).await?;
         } else {
             embeds::respond(&ctx, &i, embeds::error(Option::<String>::None, "Discord user not registered")).await?;
         }
@@ -154,26 +229,37 @@ fn handle_profile(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<d
 
     let mut username = String::new();
     for opt in &i.data.options {
-        if opt.name == "username" && opt.value.is_some() {
-            username.push_str(opt.value.as_ref().unwrap().as_str().unwrap())
+        if let (true, Some(value)) = (opt.name == "username", &opt.value) {
+            username.push_str(value.as_str().unwrap())
         }
     }
 
     Box::pin(async move {
         // make sure account exists
-        let resp = reqwest::Client::new().get(&format!("https://generals.io/api/validateUsername?u={}", urlencoding::encode(&username)))
+        let resp = reqwest::Client::new().get(format!("https://generals.io/api/validateUsername?u={}", urlencoding::encode(&username)))
             .send()
             .await?;
         let resp = resp.json::<serde_json::Value>().await?;
+        let db = DB.get().await;
         if resp.as_bool() != Some(true) {
-            embeds::respond(&ctx, &i, embeds::error(Option::<String>::None, "generals.io username does not exist")).await?;
-            return Ok(())
+            // a registered member's old name still finds them after a rename
+            let renamed = db.get_discord_by_previous_username(&username).await?;
+            let current = match renamed {
+                Some(discord) => db.get_username(discord).await?,
+                None => None,
+            };
+            match current {
+                Some(current) => username = current,
+                None => {
+                    embeds::respond(&ctx, &i, embeds::error(Option::<String>::None, "generals.io username does not exist")).await?;
+                    return Ok(())
+                }
+            }
         }
 
         eprintln!("ok");
 
         // get discord
-        let db = DB.get().await;
         let disc = db.get_discord(&username).await?.map(UserId);
 
         let e = create_user_embed(&username, disc).await?;
@@ -187,7 +273,7 @@ mod embeds {
     use std::borrow::Cow;
     use crate::palette;
 
-    use serenity::{builder::CreateEmbed, client::Context, model::interactions::application_command::ApplicationCommandInteraction};
+    use serenity::{builder::CreateEmbed, client::Context, http::AttachmentType, model::interactions::{InteractionResponseType, application_command::ApplicationCommandInteraction}};
 
     pub fn error(title: Option<impl Into<Cow<'static, str>>>, desc: impl Into<Cow<'static, str>>) -> CreateEmbed {
         let mut embed = CreateEmbed::default();
@@ -204,70 +290,24 @@ mod embeds {
             })
         })
     }
-}
 
-fn handle_register(ctx: &Context, i: &ApplicationCommandInteraction) -> Pin<Box<dyn Future<Output=Result<()>> + Send>> {
-    let (i, ctx) = (i.clone(), ctx.clone());
-    let mut user = String::new();
-    for opt in &i.data.options {
-        if opt.name == "username" {
-            user.push_str(opt.value.as_ref().unwrap_or(&serde_json::Value::Null).as_str().unwrap());
-        }
+    /// Acknowledges `i` so that slow commands can respond later with [`followup`].
+    pub fn defer<'a>(ctx: &'a Context, i: &'a ApplicationCommandInteraction) -> impl futures::Future<Output = Result<(), impl std::error::Error>> + 'a {
+        i.create_interaction_response(&ctx.http, |resp| {
+            resp.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
     }
-    let discord = i.user.id;
 
-    Box::pin(async move {
-        // check 1: username
-        if !user.to_lowercase().starts_with("[b-tier]") {
-            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), "generals.io username does not begin with [B-tier]")).await?;
-            return Ok(())
-        }
-
-        // check 2: validate username
-        let resp = reqwest::Client::new().get(&format!("https://generals.io/api/validateUsername?u={}", urlencoding::encode(&user)))
-            .send()
-            .await?;
-        let resp = resp.json::<serde_json::Value>().await?;
-        if resp.as_bool() != Some(true) {
-            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), "generals.io username does not exist")).await?;
-            return Ok(())
-        }
-
-        // check 3: check replays
-        let resp = reqwest::Client::new().get(&format!("https://generals.io/api/replaysForUsername?u={}&offset=0&count=1", urlencoding::encode(&user)))
-            .send()
-            .await?;
-        let resp = resp.json::<serde_json::Value>().await?;
-        if resp.as_array().map(|x| x.len()) != Some(1) {
-            embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), "generals.io username does not have games")).await?;
-            return Ok(())
-        }
-
-        // check 4: make sure neither exist in DB
-        let db = DB.get().await;
-        let has_discord = db.get_username(*discord.as_u64()).await?.is_some();
-        let has_username = db.get_discord(&user).await?.is_some();
-
-        if has_discord || has_username {
-                embeds::respond(&ctx, &i, embeds::error(Some("Register Error"), 
-                    "generals.io username or discord user already registered")).await?;
-                    return Ok(())
-        }
-
-        db.add_username(*discord.as_u64(), &user).await?;
-
-        i.create_interaction_response(&ctx.http, |resp| {
-            resp.interaction_response_data(|data| {
-                data.create_embed(|embed| {
-                    embed.title("Registered")
-                        .description(&format!("Username: {}\nDiscord: <@{}>", user, discord.as_u64()))
-                        .color(palette::EMBED_GAME)
-                })
-            })
+    /// Responds to a deferred interaction, optionally attaching a file.
+    pub async fn followup(ctx: &Context, i: &ApplicationCommandInteraction, e: CreateEmbed, file: Option<(Vec<u8>, &str)>) -> serenity::Result<()> {
+        i.create_followup_message(&ctx.http, |msg| {
+            if let Some((data, filename)) = file {
+                msg.add_file(AttachmentType::Bytes { data: data.into(), filename: filename.to_string() });
+            }
+            msg.add_embed(e)
         }).await?;
-
         Ok(())
-    })
+    }
 }
 
 lazy_static!{
@@ -278,14 +318,7 @@ lazy_static!{
             commands::Arg { name: "mention".into(), description: "the discord user".into(), required: false, type_: TypeId::of::<Member>() }
         ],
         handler: handle_user,
-    };
-    static ref COMMAND_REGISTER: commands::Command = commands::Command {
-        name: "register".into(),
-        description: "registers generals.io username to discord user".into(),
-        args: vec![
-            commands::Arg { name: "username".into(), description: "generals.io username".into(), required: true, type_: TypeId::of::<String>() }
-        ],
-        handler: handle_register
+        subcommands: Vec::new(),
     };
     static ref COMMAND_PROFILE: commands::Command = commands::Command {
         name: "profile".into(),
@@ -293,7 +326,8 @@ lazy_static!{
         args: vec![
             commands::Arg { name: "username".into(), description: "generals.io username".into(), required: true, type_: TypeId::of::<String>() }
         ],
-        handler: handle_profile
+        handler: handle_profile,
+        subcommands: Vec::new(),
     };
 }
 
@@ -302,10 +336,19 @@ fn on_error(ctx: &Context, i: &ApplicationCommandInteraction, error: Box<dyn std
     let i =i.clone();
     let str = format!("{}\n\n```rust\n{:?}\n```", error, error);
     Box::pin(async move {
-        embeds::respond(&ctx, &i, embeds::error(Some("Internal Error"), str)).await;
+        let e = embeds::error(Some("Internal Error"), str);
+        // the interaction may have already been deferred
+        if embeds::respond(&ctx, &i, e.clone()).await.is_err() {
+            let _ = embeds::followup(&ctx, &i, e, None).await;
+        }
     })
 }
 
+/// Discord's privileged message content intent, which serenity 0.10 has no
+/// flag for. Replay unfurling reads message text, so "Message Content Intent"
+/// must also be switched on under Bot in the Discord developer portal.
+const MESSAGE_CONTENT: u64 = 1 << 15;
+
 #[tokio::main]
 async fn main() {
     let token = env::var("DISCORD_GIO_TOKEN").expect("Expected a token in the environment");
@@ -315,11 +358,26 @@ async fn main() {
         .expect("$DISCORD_GIO_APPID must be an unsigned integer");
 
     DB.get().await;
+    tokio::spawn(jobs::snapshot_stars());
 
-    let mut commands = commands::Commands::new(vec![COMMAND_PROFILE.clone(), COMMAND_REGISTER.clone(), COMMAND_USER.clone()]);
+    let mut commands = commands::Commands::new(vec![COMMAND_PROFILE.clone(), commands::register::COMMAND_REGISTER.clone(), commands::register::COMMAND_UNREGISTER.clone(), COMMAND_USER.clone(), commands::replay::COMMAND_REPLAY.clone(), commands::h2h::COMMAND_H2H.clone(), commands::graph::COMMAND_GRAPH.clone(), commands::leaderboard::COMMAND_LEADERBOARD.clone(), commands::roles::COMMAND_ROLES.clone(), commands::unfurl::COMMAND_UNFURL.clone(), commands::announce::COMMAND_ANNOUNCE.clone(), commands::custom::COMMAND_CUSTOM.clone(), commands::lobby::COMMAND_LOBBY.clone(), commands::tournament::COMMAND_TOURNAMENT.clone(), commands::rating::COMMAND_RATING.clone(), commands::balance::COMMAND_BALANCE.clone(), commands::queue::COMMAND_QUEUE.clone(), commands::season::COMMAND_SEASON.clone(), commands::admin::COMMAND_ADMIN.clone()]);
     commands.on_error(on_error);
-
-    let mut client = serenity::Client::builder(&token).event_handler(commands).application_id(application_id).await.expect("Error creating client");
+    commands.on_message(commands::unfurl::on_message);
+    commands.on_component("lobby:", commands::lobby::on_button);
+    commands.on_component("queue:", commands::queue::on_button);
+    commands.on_component("register:", commands::register::on_button);
+
+    let mut client = serenity::Client::builder(&token)
+        .event_handler(commands)
+        .application_id(application_id)
+        .intents(GatewayIntents::non_privileged() | GatewayIntents { bits: MESSAGE_CONTENT })
+        .await.expect("Error creating client");
+    tokio::spawn(jobs::sync_roles(client.cache_and_http.http.clone()));
+    tokio::spawn(jobs::announce_games(client.cache_and_http.http.clone()));
+    tokio::spawn(jobs::run_lobbies(client.cache_and_http.http.clone()));
+    tokio::spawn(jobs::verify_matches(client.cache_and_http.http.clone()));
+    tokio::spawn(jobs::run_queue(client.cache_and_http.http.clone()));
+    tokio::spawn(jobs::archive_seasons(client.cache_and_http.http.clone()));
 
     if let Err(why) = client.start().await {
         println!("Client error: {:?}", why);
@@ -0,0 +1,32 @@
+//! Matchmaking queue and the matches it pops
+
+use crate::stars::Mode;
+
+pub struct QueueEntry {
+    pub guild: u64,
+    /// Channel the player queued from, where their match is posted.
+    pub channel: u64,
+    pub discord: u64,
+    pub mode: Mode,
+    pub stars: f64,
+    /// Seconds since the epoch.
+    pub joined: u64,
+}
+
+/// A popped match waiting for its ready check, identified by its message.
+pub struct QueueMatch {
+    pub message: u64,
+    pub guild: u64,
+    pub channel: u64,
+    pub mode: Mode,
+    pub code: String,
+    pub created: u64,
+}
+
+/// A player in a popped match.
+pub struct QueuePlayer {
+    pub discord: u64,
+    pub stars: f64,
+    pub joined: u64,
+    pub ready: bool,
+}
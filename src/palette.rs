@@ -1,4 +1,27 @@
 use serenity::utils::Colour;
 
 pub const EMBED_GAME: Colour = Colour(0x008080);
-pub const EMBED_ERROR: Colour = Colour(0xee1100);
\ No newline at end of file
+pub const EMBED_ERROR: Colour = Colour(0xee1100);
+
+/// generals.io player colours, indexed by `Replay::player_colors`.
+pub const PLAYERS: [Colour; 12] = [
+    Colour(0xff0000), // red
+    Colour(0x2792ff), // light blue
+    Colour(0x008000), // green
+    Colour(0x008080), // teal
+    Colour(0xf58231), // orange
+    Colour(0xf032e6), // pink
+    Colour(0x800080), // purple
+    Colour(0x800000), // maroon
+    Colour(0xb09f30), // yellow
+    Colour(0x9a6324), // brown
+    Colour(0x0000ff), // blue
+    Colour(0x483d8b), // purple blue
+];
+
+pub fn player(color: u32) -> Colour {
+    PLAYERS[color as usize % PLAYERS.len()]
+}
+
+/// Closest square emoji to each of [`PLAYERS`], for text that can't be coloured.
+pub const PLAYER_EMOJI: [&str; 12] = ["🟥", "🟦", "🟩", "🟦", "🟧", "🟪", "🟪", "🟫", "🟨", "🟫", "🟦", "🟪"];
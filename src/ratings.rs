@@ -0,0 +1,151 @@
+//! Server-local ratings from custom games between registered members
+//!
+//! Only games are stored; ratings are recomputed from them in order, with
+//! every game its own Glicko-2 rating period.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{glicko::Rating, replay::{metadata::Metadata, replay::Replay}, stars::ModeError, DB};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Pool {
+    Duel,
+    Ffa,
+    Teams,
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Pool::Duel => "1v1",
+            Pool::Ffa => "FFA",
+            Pool::Teams => "Teams",
+        })
+    }
+}
+
+impl FromStr for Pool {
+    type Err = ModeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "duel" | "1v1" => Pool::Duel,
+            "ffa" => Pool::Ffa,
+            "teams" | "team" | "2v2" => Pool::Teams,
+            _ => return Err(ModeError(format!("invalid pool '{}'", s)))
+        })
+    }
+}
+
+/// A rated custom game.
+pub struct RatedGame {
+    pub id: String,
+    pub guild: u64,
+    pub pool: Pool,
+    /// Seconds since the epoch.
+    pub started: u64,
+    /// Registered usernames in finishing order.
+    pub ranking: Vec<String>,
+    /// Team of each player in `ranking`, for team games.
+    pub teams: Option<Vec<u32>>,
+}
+
+impl RatedGame {
+    /// Builds a game from its metadata if every player is registered, using
+    /// `replay` for teams.
+    pub fn new(guild: u64, game: &Metadata, replay: Option<&Replay>, users: &[(u64, String)]) -> Option<RatedGame> {
+        let ranking = game.ranking.iter()
+            .map(|p| users.iter()
+                .find(|(_, u)| u.eq_ignore_ascii_case(&p.name) || u.eq_ignore_ascii_case(&p.current_name))
+                .map(|(_, u)| u.clone()))
+            .collect::<Option<Vec<_>>>()?;
+
+        let teams = replay.and_then(|r| {
+            let teams = r.teams.as_ref()?;
+            game.ranking.iter()
+                .map(|p| r.usernames.iter().position(|u| u.eq_ignore_ascii_case(&p.name)).and_then(|k| teams.get(k).copied()))
+                .collect::<Option<Vec<_>>>()
+        });
+        // teams of one are just FFA
+        let teams = teams.filter(|t| {
+            let mut distinct = t.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            distinct.len() < t.len()
+        });
+
+        let pool = match (&teams, ranking.len()) {
+            (Some(_), _) => Pool::Teams,
+            (None, 2) => Pool::Duel,
+            (None, _) => Pool::Ffa,
+        };
+        Some(RatedGame { id: game.id.clone(), guild, pool, started: game.started / 1000, ranking, teams })
+    }
+
+    /// Whether `a` finished ahead of `b`, or `None` for teammates.
+    fn beat(&self, a: usize, b: usize) -> Option<bool> {
+        match &self.teams {
+            Some(teams) if teams[a] == teams[b] => None,
+            Some(teams) => {
+                // a team places where its best player did
+                let place = |team: u32| teams.iter().position(|&t| t == team).unwrap();
+                Some(place(teams[a]) < place(teams[b]))
+            },
+            None => Some(a < b),
+        }
+    }
+}
+
+/// Rating history of one player as (time, rating after that game).
+pub type History = Vec<(u64, Rating)>;
+
+/// Replays `games` in order, returning every player's rating history keyed
+/// by lowercase username. Ratings are soft reset at each time in `resets`,
+/// taking effect from a player's next game.
+pub fn compute(games: &[RatedGame], resets: &[u64]) -> HashMap<String, History> {
+    let mut histories: HashMap<String, History> = HashMap::new();
+    for game in games {
+        let before = game.ranking.iter()
+            .map(|u| match histories.get(&u.to_lowercase()).and_then(|h| h.last()) {
+                Some(&(time, rating)) => resets.iter()
+                    .filter(|&&reset| time < reset && reset <= game.started)
+                    .fold(rating, |r, _| r.soft_reset()),
+                None => Rating::default(),
+            })
+            .collect::<Vec<_>>();
+
+        // every pair of opponents counts as a result
+        for (k, username) in game.ranking.iter().enumerate() {
+            let results = (0..game.ranking.len())
+                .filter(|&j| j != k)
+                .filter_map(|j| game.beat(k, j).map(|won| (before[j], if won { 1.0 } else { 0.0 })))
+                .collect::<Vec<_>>();
+            let after = before[k].update(&results);
+            histories.entry(username.to_lowercase()).or_default().push((game.started, after));
+        }
+    }
+    histories
+}
+
+/// Rating histories of `pool` in `guild`, with the games they came from.
+pub async fn histories(guild: u64, pool: Pool) -> crate::Result<(Vec<RatedGame>, HashMap<String, History>)> {
+    let db = DB.get().await;
+    let games = db.get_rated_games(guild, pool).await?;
+    let resets = db.get_seasons(guild).await?
+        .into_iter()
+        .filter(|s| s.soft_reset)
+        .map(|s| s.start)
+        .collect::<Vec<_>>();
+    let histories = compute(&games, &resets);
+    Ok((games, histories))
+}
+
+/// Stores `game` unless it was already rated, returning whether it was new.
+pub async fn record(game: &RatedGame) -> crate::Result<bool> {
+    let db = DB.get().await;
+    if db.has_rated_game(game.guild, &game.id).await? {
+        return Ok(false);
+    }
+    db.add_rated_game(game).await?;
+    eprintln!("ratings: recorded {} {} in {}", game.pool, game.id, game.guild);
+    Ok(true)
+}
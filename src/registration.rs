@@ -0,0 +1,32 @@
+//! Proof of generals.io account ownership
+
+use crate::replay::{self, metadata};
+
+/// Recent games of the account searched for the challenge.
+const VERIFY_SEARCH: usize = 10;
+
+/// A pending registration awaiting proof of ownership.
+pub struct Verification {
+    pub discord: u64,
+    pub username: String,
+    pub code: String,
+    /// Seconds since the epoch.
+    pub issued: u64,
+}
+
+/// Whether a custom game `username` played since the challenge was issued has
+/// them typing its code in chat.
+pub async fn find_proof(v: &Verification) -> crate::Result<bool> {
+    let games = metadata::fetch(&v.username, 0, VERIFY_SEARCH).await?;
+    for game in games.iter().filter(|g| g.type_ == "custom" && g.started / 1000 >= v.issued) {
+        let replay = replay::fetch(&game.id).await?;
+        let player = match replay.usernames.iter().position(|u| u.eq_ignore_ascii_case(&v.username)) {
+            Some(player) => player,
+            None => continue,
+        };
+        if replay.chat.iter().any(|c| c.player_index as usize == player && c.message.to_lowercase().contains(&v.code.to_lowercase())) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
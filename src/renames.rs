@@ -0,0 +1,36 @@
+//! Follows generals.io renames of registered accounts
+//!
+//! Replay listings give each player's name at the time of the game and their
+//! current name, so a member's own listing reveals when they were renamed.
+
+use crate::{replay::metadata::Metadata, DB};
+
+/// Updates `username`'s registration if `games`, their own replay listing
+/// newest first, show the account was renamed, returning the rename as
+/// (old, new). Only the newest game counts: other players' names are left
+/// alone, as a listing only vouches for its owner, and older games may list
+/// someone who held the name before.
+pub async fn detect(username: &str, games: &[Metadata]) -> crate::Result<Option<(String, String)>> {
+    let current = games.first()
+        .into_iter()
+        .flat_map(|g| &g.ranking)
+        .find(|p| p.name.eq_ignore_ascii_case(username) && !p.current_name.is_empty())
+        .map(|p| p.current_name.clone());
+    let current = match current {
+        Some(current) if !current.eq_ignore_ascii_case(username) => current,
+        _ => return Ok(None),
+    };
+
+    let db = DB.get().await;
+    let discord = db.get_discord(username).await?;
+    let discord = match discord {
+        Some(discord) => discord,
+        None => return Ok(None),
+    };
+    if !db.rename_username(discord, username, &current, crate::unix_time()).await? {
+        eprintln!("renames: {} became {}, which is registered to someone else", username, current);
+        return Ok(None);
+    }
+    eprintln!("renames: {} ({}) is now {}", username, discord, current);
+    Ok(Some((username.to_string(), current)))
+}
@@ -0,0 +1,383 @@
+//! Replay simulator
+//!
+//! Steps through a [`Replay`] using generals.io rules and reconstructs the
+//! owner and army of every tile for any turn.
+
+use super::replay::{Replay, TileIndex, PlayerIndex, Turn};
+
+/// Ticks between army growth on generals and owned cities (one full turn).
+const GROWTH_INTERVAL: Turn = 2;
+/// Ticks between the land bonus on every owned tile (25 full turns).
+const LAND_BONUS_INTERVAL: Turn = 50;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Terrain {
+    Plain,
+    Mountain,
+    City,
+    General,
+    Swamp,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    pub terrain: Terrain,
+    pub owner: Option<PlayerIndex>,
+    pub army: u32,
+}
+
+#[derive(Clone)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<Tile>,
+}
+
+impl Board {
+    /// An empty board if `width` by `height` is more tiles than a
+    /// [`TileIndex`] can address, which only a corrupt replay has.
+    fn new(width: u32, height: u32) -> Board {
+        let (width, height) = match width.checked_mul(height) {
+            Some(size) if size <= TileIndex::MAX as u32 + 1 => (width, height),
+            _ => (0, 0),
+        };
+        Board {
+            width,
+            height,
+            tiles: vec![Tile { terrain: Terrain::Plain, owner: None, army: 0 }; (width * height) as usize],
+        }
+    }
+
+    pub fn tile(&self, index: TileIndex) -> &Tile {
+        &self.tiles[index as usize]
+    }
+
+    pub fn is_adjacent(&self, a: TileIndex, b: TileIndex) -> bool {
+        let (a, b, w) = (a as u32, b as u32, self.width);
+        let (ax, ay, bx, by) = (a % w, a / w, b % w, b / w);
+        (ax == bx && (ay as i64 - by as i64).abs() == 1) || (ay == by && (ax as i64 - bx as i64).abs() == 1)
+    }
+
+    /// Number of tiles owned by `player`.
+    pub fn land(&self, player: PlayerIndex) -> u32 {
+        self.tiles.iter().filter(|t| t.owner == Some(player)).count() as u32
+    }
+
+    /// Total army owned by `player`.
+    pub fn army(&self, player: PlayerIndex) -> u64 {
+        self.tiles.iter().filter(|t| t.owner == Some(player)).map(|t| t.army as u64).sum()
+    }
+
+    /// Number of cities owned by `player`, not counting generals.
+    pub fn cities(&self, player: PlayerIndex) -> u32 {
+        self.tiles.iter().filter(|t| t.owner == Some(player) && t.terrain == Terrain::City).count() as u32
+    }
+}
+
+/// A general capture.
+#[derive(Clone, Copy, Debug)]
+pub struct Kill {
+    pub turn: Turn,
+    pub killer: PlayerIndex,
+    pub victim: PlayerIndex,
+}
+
+pub struct Game<'a> {
+    replay: &'a Replay,
+    board: Board,
+    turn: Turn,
+    generals: Vec<Option<TileIndex>>,
+    dead: Vec<bool>,
+    kills: Vec<Kill>,
+    move_index: usize,
+    afk_index: usize,
+}
+
+impl<'a> Game<'a> {
+    /// Sets up the starting board. Tiles outside the map, which only a
+    /// corrupt replay has, are ignored.
+    pub fn new(replay: &'a Replay) -> Game<'a> {
+        let mut board = Board::new(replay.map_width, replay.map_height);
+        for &m in &replay.mountains {
+            if let Some(tile) = board.tiles.get_mut(m as usize) {
+                tile.terrain = Terrain::Mountain;
+            }
+        }
+        for (&c, &army) in replay.cities.iter().zip(&replay.city_armies) {
+            if let Some(tile) = board.tiles.get_mut(c as usize) {
+                *tile = Tile { terrain: Terrain::City, owner: None, army };
+            }
+        }
+        for (&n, &army) in replay.neutrals.iter().zip(&replay.neutral_armies) {
+            if let Some(tile) = board.tiles.get_mut(n as usize) {
+                tile.army = army;
+            }
+        }
+        for &s in &replay.swamps {
+            if let Some(tile) = board.tiles.get_mut(s as usize) {
+                tile.terrain = Terrain::Swamp;
+            }
+        }
+        for (i, &g) in replay.generals.iter().enumerate() {
+            if let Some(tile) = board.tiles.get_mut(g as usize) {
+                *tile = Tile { terrain: Terrain::General, owner: Some(i as PlayerIndex), army: 1 };
+            }
+        }
+
+        Game {
+            replay,
+            board,
+            turn: 0,
+            generals: replay.generals.iter().map(|&g| Some(g)).collect(),
+            dead: vec![false; replay.generals.len()],
+            kills: Vec::new(),
+            move_index: 0,
+            afk_index: 0,
+        }
+    }
+
+    /// Simulates `replay` up to the start of `turn`.
+    pub fn at(replay: &'a Replay, turn: Turn) -> Game<'a> {
+        let mut game = Game::new(replay);
+        while game.turn < turn && !game.is_finished() {
+            game.step();
+        }
+        game
+    }
+
+    /// The tick after the last move or AFK in `replay`.
+    pub fn last_turn(replay: &Replay) -> Turn {
+        let last_move = replay.moves.iter().map(|m| m.turn).max().unwrap_or(0);
+        let last_afk = replay.afks.iter().map(|a| a.turn).max().unwrap_or(0);
+        last_move.max(last_afk) + 1
+    }
+
+    pub fn replay(&self) -> &'a Replay {
+        self.replay
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn turn(&self) -> Turn {
+        self.turn
+    }
+
+    pub fn kills(&self) -> &[Kill] {
+        &self.kills
+    }
+
+    /// Whether `player` has neither lost their general nor gone AFK.
+    pub fn is_alive(&self, player: PlayerIndex) -> bool {
+        self.dead.get(player as usize).is_some_and(|dead| !dead)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.move_index >= self.replay.moves.len() && self.afk_index >= self.replay.afks.len()
+    }
+
+    /// Applies the moves and AFKs of the current tick, then advances to the
+    /// next one. Out-of-order entries from earlier ticks are applied late
+    /// rather than stalling the replay.
+    pub fn step(&mut self) {
+        while let Some(m) = self.replay.moves.get(self.move_index) {
+            if m.turn > self.turn { break; }
+            self.attack(m.player_index, m.start, m.end, m.is50);
+            self.move_index += 1;
+        }
+
+        while let Some(afk) = self.replay.afks.get(self.afk_index) {
+            if afk.turn > self.turn { break; }
+            let player = afk.index as PlayerIndex;
+            // the first AFK marks the player as dead, the second (or any AFK after
+            // a general capture) turns what's left of their land neutral
+            match self.dead.get_mut(afk.index) {
+                Some(dead) if *dead => self.neutralize(player),
+                Some(dead) => *dead = true,
+                None => {},
+            }
+            self.afk_index += 1;
+        }
+
+        self.turn += 1;
+
+        if self.turn.is_multiple_of(GROWTH_INTERVAL) {
+            for tile in self.board.tiles.iter_mut() {
+                if tile.owner.is_none() { continue; }
+                match tile.terrain {
+                    Terrain::General | Terrain::City => tile.army += 1,
+                    Terrain::Swamp => {
+                        tile.army = tile.army.saturating_sub(1);
+                        if tile.army == 0 {
+                            tile.owner = None;
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        if self.turn.is_multiple_of(LAND_BONUS_INTERVAL) {
+            for tile in self.board.tiles.iter_mut() {
+                if tile.owner.is_some() {
+                    tile.army += 1;
+                }
+            }
+        }
+    }
+
+    pub fn is_teammate(&self, a: PlayerIndex, b: PlayerIndex) -> bool {
+        match &self.replay.teams {
+            Some(teams) => teams.get(a as usize) == teams.get(b as usize),
+            None => a == b,
+        }
+    }
+
+    fn attack(&mut self, player: PlayerIndex, start: TileIndex, end: TileIndex, is50: bool) {
+        if start as usize >= self.board.tiles.len() || end as usize >= self.board.tiles.len() {
+            return;
+        }
+
+        let from = *self.board.tile(start);
+        let to = *self.board.tile(end);
+        if from.owner != Some(player) || to.terrain == Terrain::Mountain || !self.board.is_adjacent(start, end) {
+            return;
+        }
+
+        let reserve = if is50 { from.army.div_ceil(2) } else { 1 };
+        if from.army <= reserve {
+            return;
+        }
+        let force = from.army - reserve;
+        self.board.tiles[start as usize].army = reserve;
+
+        let friendly = to.owner.is_some_and(|owner| self.is_teammate(owner, player));
+        let target = &mut self.board.tiles[end as usize];
+        match friendly {
+            true => {
+                target.army += force;
+                if to.terrain != Terrain::General {
+                    target.owner = Some(player);
+                }
+            },
+            false => {
+                if target.army >= force {
+                    target.army -= force;
+                } else {
+                    target.army = force - target.army;
+                    target.owner = Some(player);
+                }
+            }
+        }
+
+        // general capture
+        if let Some(victim) = to.owner {
+            if to.terrain == Terrain::General && self.board.tile(end).owner != to.owner {
+                self.kills.push(Kill { turn: self.turn, killer: player, victim });
+                self.dead[victim as usize] = true;
+                self.generals[victim as usize] = None;
+                self.board.tiles[end as usize].terrain = Terrain::City;
+                for tile in self.board.tiles.iter_mut() {
+                    if tile.owner == Some(victim) {
+                        tile.owner = Some(player);
+                        tile.army = (tile.army as f64 / 2.0).round() as u32;
+                    }
+                }
+            }
+        }
+    }
+
+    fn neutralize(&mut self, player: PlayerIndex) {
+        if let Some(tile) = self.generals[player as usize].take().and_then(|g| self.board.tiles.get_mut(g as usize)) {
+            tile.terrain = Terrain::City;
+        }
+        for tile in self.board.tiles.iter_mut() {
+            if tile.owner == Some(player) {
+                tile.owner = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Game, Terrain};
+    use crate::replay::{lzstring, replay::{Move, Replay, Surrender}};
+
+    /// A 3x1 duel: Bob moves out of his general on tick 10 and Alice takes
+    /// it on tick 11.
+    fn fixture() -> Replay {
+        let json = lzstring::decompress_from_u8(include_bytes!("fixtures/capture.gior")).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn tiles(game: &Game) -> Vec<(Terrain, Option<u8>, u32)> {
+        game.board().tiles.iter().map(|t| (t.terrain, t.owner, t.army)).collect()
+    }
+
+    #[test]
+    fn generals_grow_every_turn() {
+        let replay = fixture();
+        let game = Game::at(&replay, 10);
+        assert_eq!(tiles(&game), vec![(Terrain::General, Some(0), 6), (Terrain::General, Some(1), 6), (Terrain::Plain, None, 0)]);
+    }
+
+    #[test]
+    fn general_capture() {
+        let replay = fixture();
+        let game = Game::at(&replay, Game::last_turn(&replay));
+        assert_eq!(game.turn(), 12);
+        // the captured general becomes a city and Bob's land comes over at half strength
+        assert_eq!(tiles(&game), vec![(Terrain::General, Some(0), 2), (Terrain::City, Some(0), 5), (Terrain::Plain, Some(0), 3)]);
+        let kills = game.kills().iter().map(|k| (k.turn, k.killer, k.victim)).collect::<Vec<_>>();
+        assert_eq!(kills, vec![(11, 0, 1)]);
+        assert!(game.is_alive(0));
+        assert!(!game.is_alive(1));
+        assert_eq!((game.board().land(0), game.board().army(0), game.board().cities(0)), (3, 10, 1));
+    }
+
+    #[test]
+    fn second_afk_neutralizes() {
+        let mut replay = fixture();
+        replay.moves.truncate(1);
+        replay.afks = vec![Surrender { index: 1, turn: 12 }, Surrender { index: 1, turn: 14 }];
+
+        let game = Game::at(&replay, 13);
+        assert!(!game.is_alive(1));
+        assert_eq!(tiles(&game)[1], (Terrain::General, Some(1), 2));
+
+        let game = Game::at(&replay, 15);
+        assert_eq!(tiles(&game)[1..], [(Terrain::City, None, 3), (Terrain::Plain, None, 5)]);
+    }
+
+    #[test]
+    fn oversized_board_is_empty() {
+        let mut replay = fixture();
+        replay.map_width = u32::MAX;
+        replay.map_height = 2;
+        let game = Game::at(&replay, Game::last_turn(&replay));
+        assert!(game.board().tiles.is_empty());
+        assert!(game.kills().is_empty());
+    }
+
+    #[test]
+    fn last_turn_of_unsorted_moves() {
+        let mut replay = fixture();
+        replay.moves.swap(0, 1);
+        assert_eq!(Game::last_turn(&replay), 12);
+    }
+
+    #[test]
+    fn ignores_out_of_range_indices() {
+        let mut replay = fixture();
+        replay.mountains.push(99);
+        replay.swamps.push(100);
+        replay.moves.insert(0, Move { player_index: 7, start: 0, end: 50, is50: false, turn: 1 });
+        replay.afks.push(Surrender { index: 5, turn: 11 });
+
+        let game = Game::at(&replay, Game::last_turn(&replay));
+        assert_eq!(game.kills().len(), 1);
+        assert!(!game.is_alive(5));
+    }
+}
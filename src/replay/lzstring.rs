@@ -0,0 +1,119 @@
+//! Decompression for `.gior` files, which are
+//! `LZString.compressToUint8Array` of the replay JSON.
+
+struct Bits<'a> {
+    data: &'a [u16],
+    val: u16,
+    position: u16,
+    index: usize,
+}
+
+impl<'a> Bits<'a> {
+    const RESET: u16 = 32768;
+
+    fn new(data: &'a [u16]) -> Bits<'a> {
+        Bits { data, val: data[0], position: Self::RESET, index: 1 }
+    }
+
+    fn read(&mut self, n: u32) -> u32 {
+        let mut bits = 0;
+        for i in 0..n {
+            let bit = self.val & self.position != 0;
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = Self::RESET;
+                self.val = self.data.get(self.index).copied().unwrap_or(0);
+                self.index += 1;
+            }
+            bits |= (bit as u32) << i;
+        }
+        bits
+    }
+}
+
+/// Returns `None` if the input is malformed.
+pub fn decompress_from_u8(compressed: &[u8]) -> Option<String> {
+    let data = compressed.chunks(2)
+        .map(|c| (c[0] as u16) << 8 | c.get(1).copied().unwrap_or(0) as u16)
+        .collect::<Vec<_>>();
+    if data.is_empty() {
+        return None;
+    }
+
+    let mut bits = Bits::new(&data);
+    let mut dictionary: Vec<Vec<u16>> = vec![Vec::new(); 3];
+    let mut enlarge_in = 4u32;
+    let mut num_bits = 3;
+
+    let c = match bits.read(2) {
+        0 => bits.read(8) as u16,
+        1 => bits.read(16) as u16,
+        _ => return Some(String::new()),
+    };
+    dictionary.push(vec![c]);
+    let mut w = vec![c];
+    let mut result = w.clone();
+
+    loop {
+        if bits.index > data.len() {
+            return None;
+        }
+
+        let mut c = bits.read(num_bits) as usize;
+        match c {
+            0 | 1 => {
+                let ch = bits.read(if c == 0 { 8 } else { 16 }) as u16;
+                dictionary.push(vec![ch]);
+                c = dictionary.len() - 1;
+                enlarge_in -= 1;
+            },
+            2 => return String::from_utf16(&result).ok(),
+            _ => {}
+        }
+
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+
+        let entry = if c < dictionary.len() {
+            dictionary[c].clone()
+        } else if c == dictionary.len() {
+            let mut entry = w.clone();
+            entry.push(w[0]);
+            entry
+        } else {
+            return None;
+        };
+        result.extend_from_slice(&entry);
+
+        let mut next = w;
+        next.push(entry[0]);
+        dictionary.push(next);
+        enlarge_in -= 1;
+        w = entry;
+
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decompress_from_u8;
+
+    const FIXTURE: &[u8] = include_bytes!("fixtures/capture.gior");
+    const FIXTURE_JSON: &str = r#"[7,"fixture",3,1,["Alice","Bob"],[0,0],[],[],[0,1],[],[[1,1,2,false,10],[0,0,1,false,11]],[],null,null,[],[],[],[["gg","Alice",0,12]],[0,1],[],[1,0.5,0.5,0.5]]"#;
+
+    #[test]
+    fn decompresses_replay() {
+        assert_eq!(decompress_from_u8(FIXTURE).as_deref(), Some(FIXTURE_JSON));
+    }
+
+    #[test]
+    fn rejects_empty_input() {
+        assert_eq!(decompress_from_u8(&[]), None);
+    }
+}
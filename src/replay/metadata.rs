@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize,Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    #[serde(rename = "currentName", default)]
+    pub current_name: String,
+    // hopefully they implement fractional stars!!!!
+    #[serde(default)]
+    pub stars: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub id: String,
+    pub started: u64,
+    pub turns: u64,
+    pub ranking: Vec<Player>
+}
+
+impl Metadata {
+    /// Zero-based finishing position of `username`, matching old or current names.
+    pub fn placement(&self, username: &str) -> Option<usize> {
+        self.ranking.iter().position(|p| p.name.eq_ignore_ascii_case(username) || p.current_name.eq_ignore_ascii_case(username))
+    }
+
+    /// Number of players on the winning side.
+    pub fn winners(&self) -> usize {
+        if self.type_ == "2v2" { 2 } else { 1 }
+    }
+
+    pub fn is_win(&self, username: &str) -> Option<bool> {
+        self.placement(username).map(|p| p < self.winners())
+    }
+
+    /// Human-readable name for `type_`.
+    pub fn mode(&self) -> &str {
+        match self.type_.as_str() {
+            "classic" => "FFA",
+            "1v1" => "1v1",
+            "2v2" => "2v2",
+            "custom" => "Custom",
+            other => other,
+        }
+    }
+}
+
+/// Fetches replay metadata for `username`, newest first.
+pub async fn fetch(username: &str, offset: usize, count: usize) -> crate::Result<Vec<Metadata>> {
+    Ok(reqwest::get(format!("https://generals.io/api/replaysForUsername?u={}&offset={}&count={}", urlencoding::encode(username), offset, count))
+        .await?
+        .json()
+        .await?)
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Form {
+    pub wins: u32,
+    pub losses: u32,
+    placements: u64,
+}
+
+impl Form {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses
+    }
+
+    /// One-based average placement.
+    pub fn average_placement(&self) -> f64 {
+        if self.games() == 0 { 0.0 } else { self.placements as f64 / self.games() as f64 + 1.0 }
+    }
+}
+
+/// Results of `username` in the first `per_mode` games of each mode in
+/// `games`, keyed by [`Metadata::mode`].
+pub fn form(games: &[Metadata], username: &str, per_mode: usize) -> BTreeMap<String, Form> {
+    let mut out: BTreeMap<String, Form> = BTreeMap::new();
+    for game in games {
+        if let Some(placement) = game.placement(username) {
+            let form = out.entry(game.mode().to_string()).or_default();
+            if form.games() as usize >= per_mode {
+                continue;
+            }
+            if placement < game.winners() { form.wins += 1 } else { form.losses += 1 }
+            form.placements += placement as u64;
+        }
+    }
+    out
+}
+
+/// Length of the current winning (positive) or losing (negative) streak.
+pub fn streak(games: &[Metadata], username: &str) -> i32 {
+    let mut results = games.iter().filter_map(|g| g.is_win(username));
+    let first = match results.next() {
+        Some(first) => first,
+        None => return 0,
+    };
+    let len = 1 + results.take_while(|&r| r == first).count() as i32;
+    if first { len } else { -len }
+}
@@ -0,0 +1,32 @@
+pub mod metadata;
+#[allow(clippy::module_inception)]
+pub mod replay;
+pub mod game;
+pub mod render;
+pub mod stats;
+mod lzstring;
+
+use std::fmt;
+
+#[derive(Debug)]
+pub struct DecodeError(pub String);
+
+impl std::error::Error for DecodeError {}
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Downloads and decodes the replay with the given ID.
+pub async fn fetch(id: &str) -> crate::Result<replay::Replay> {
+    let resp = reqwest::get(format!("https://generalsio-replays-na.s3.amazonaws.com/{}.gior", urlencoding::encode(id)))
+        .await?;
+    if !resp.status().is_success() {
+        return Err(Box::new(DecodeError(format!("replay '{}' not found", id))));
+    }
+
+    let bytes = resp.bytes().await?;
+    let json = lzstring::decompress_from_u8(&bytes).ok_or_else(|| DecodeError(format!("replay '{}' is corrupt", id)))?;
+    Ok(serde_json::from_str(&json)?)
+}
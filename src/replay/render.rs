@@ -0,0 +1,223 @@
+//! Board rendering
+//!
+//! Draws a simulated [`Game`] onto an RGB canvas. Everything is done by hand
+//! so there are no fonts or browsers involved.
+
+use std::collections::HashMap;
+
+use super::{game::{Game, Terrain}, replay::{Replay, Turn}};
+use crate::palette;
+
+pub const TILE_SIZE: u32 = 32;
+
+const EMPTY: [u8; 3] = [0xdc, 0xdc, 0xdc];
+const NEUTRAL: [u8; 3] = [0x80, 0x80, 0x80];
+const MOUNTAIN: [u8; 3] = [0xbb, 0xbb, 0xbb];
+const GRID: [u8; 3] = [0x20, 0x20, 0x20];
+const ICON: [u8; 3] = [0x40, 0x40, 0x40];
+const SWAMP: [u8; 3] = [0x4a, 0x6b, 0x3a];
+const TEXT: [u8; 3] = [0xff, 0xff, 0xff];
+
+/// 3x5 digits, one row per byte, high bit on the left.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    /// RGB, row-major
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas { width, height, pixels: vec![0; (width * height * 3) as usize] }
+    }
+
+    pub fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, color: [u8; 3]) {
+        for py in y..(y + h).min(self.height) {
+            for px in x..(x + w).min(self.width) {
+                let i = ((py * self.width + px) * 3) as usize;
+                self.pixels[i..i + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Draws a `thickness` pixel wide line between two points.
+    pub fn draw_line(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64), thickness: u32, color: [u8; 3]) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+        for k in 0..=steps {
+            let x = x0 + (x1 - x0) * k / steps;
+            let y = y0 + (y1 - y0) * k / steps;
+            if x >= 0 && y >= 0 {
+                self.fill_rect(x as u32, y as u32, thickness, thickness, color);
+            }
+        }
+    }
+
+    /// Draws `n` centered on (`cx`, `cy`), shrinking the digits if they would not fit in `max_width`.
+    pub fn draw_number(&mut self, n: u64, cx: u32, cy: u32, max_width: u32, color: [u8; 3]) {
+        let digits = n.to_string().bytes().map(|b| (b - b'0') as usize).collect::<Vec<_>>();
+        let text_width = |scale: u32| digits.len() as u32 * 4 * scale - scale;
+        let scale = if text_width(2) <= max_width { 2 } else { 1 };
+
+        let mut x = cx.saturating_sub(text_width(scale) / 2);
+        let y = cy.saturating_sub(5 * scale / 2);
+        for d in digits {
+            for (row, bits) in DIGITS[d].iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) != 0 {
+                        self.fill_rect(x + col * scale, y + row as u32 * scale, scale, scale, color);
+                    }
+                }
+            }
+            x += 4 * scale;
+        }
+    }
+
+    pub fn to_png(&self) -> crate::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+            encoder.set_color(png::ColorType::RGB);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(&self.pixels)?;
+        }
+        Ok(out)
+    }
+
+    /// Converts to a GIF frame. Boards only use a handful of colours,
+    /// so an exact palette is built instead of quantizing.
+    pub fn to_gif_frame(&self) -> gif::Frame<'static> {
+        let mut colors: HashMap<&[u8], u8> = HashMap::new();
+        let mut palette = Vec::new();
+        let indices = self.pixels.chunks(3).map(|px| {
+            let next = colors.len();
+            *colors.entry(px).or_insert_with(|| {
+                palette.extend_from_slice(px);
+                next.min(255) as u8
+            })
+        }).collect::<Vec<_>>();
+        gif::Frame::from_palette_pixels(self.width as u16, self.height as u16, &indices, &palette, None)
+    }
+}
+
+fn rgb(color: serenity::utils::Colour) -> [u8; 3] {
+    [color.r(), color.g(), color.b()]
+}
+
+/// Draws the board of `game` at its current turn.
+pub fn draw(game: &Game) -> Canvas {
+    let board = game.board();
+    let replay = game.replay();
+    let mut canvas = Canvas::new(board.width * TILE_SIZE + 1, board.height * TILE_SIZE + 1);
+    canvas.fill_rect(0, 0, canvas.width, canvas.height, GRID);
+
+    for (i, tile) in board.tiles.iter().enumerate() {
+        let x = (i as u32 % board.width) * TILE_SIZE + 1;
+        let y = (i as u32 / board.width) * TILE_SIZE + 1;
+        let size = TILE_SIZE - 1;
+
+        let background = match (tile.owner, tile.terrain) {
+            (Some(owner), _) => rgb(palette::player(replay.player_colors.get(owner as usize).copied().unwrap_or(owner as u32))),
+            (None, Terrain::Mountain) => MOUNTAIN,
+            (None, Terrain::Swamp) => SWAMP,
+            (None, Terrain::City) | (None, Terrain::General) => NEUTRAL,
+            (None, Terrain::Plain) if tile.army > 0 => NEUTRAL,
+            (None, Terrain::Plain) => EMPTY,
+        };
+        canvas.fill_rect(x, y, size, size, background);
+
+        match tile.terrain {
+            Terrain::Mountain => {
+                // stacked bars make a rough triangle
+                for row in 0..size / 2 {
+                    let half = row * 3 / 4 + 1;
+                    canvas.fill_rect(x + size / 2 - half, y + size / 4 + row, half * 2, 1, ICON);
+                }
+            },
+            Terrain::City => {
+                // battlements
+                let (bx, by) = (x + 4, y + 4);
+                for k in 0..3 {
+                    canvas.fill_rect(bx + k * (size - 8) / 3, by, (size - 8) / 6, 3, ICON);
+                }
+                canvas.fill_rect(bx, by + 3, size - 8, 2, ICON);
+            },
+            Terrain::General => {
+                // crown
+                canvas.fill_rect(x + 4, y + 3, size - 8, 2, ICON);
+                for k in 0..3 {
+                    canvas.fill_rect(x + 4 + k * (size - 10) / 2, y + 1, 2, 2, ICON);
+                }
+            },
+            Terrain::Swamp => {
+                for k in 0..2 {
+                    canvas.fill_rect(x + 3, y + size - 6 + k * 3, size - 6, 1, ICON);
+                }
+            },
+            Terrain::Plain => {}
+        }
+
+        if tile.army > 0 {
+            canvas.draw_number(tile.army as u64, x + size / 2, y + size / 2 + 2, size - 2, TEXT);
+        }
+    }
+
+    canvas
+}
+
+/// Renders ticks `start..=end` of `replay` as an animated GIF no larger than `max_bytes`.
+///
+/// Plays at the replay's speed. If the result would be too large, frames are
+/// skipped (doubling the gap each time) until it fits.
+pub fn animate(replay: &Replay, start: Turn, end: Turn, max_bytes: usize) -> crate::Result<Option<Vec<u8>>> {
+    let speed = if replay.options.speed > 0.0 { replay.options.speed } else { 1.0 };
+    // one tick is half a second at 1x
+    let tick_delay = 50.0 / speed;
+
+    let mut step: Turn = 1;
+    while step <= end.saturating_sub(start).max(1) {
+        let mut game = Game::at(replay, start);
+        let mut frame = draw(&game);
+        let mut encoder = gif::Encoder::new(Vec::new(), frame.width as u16, frame.height as u16, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        let delay = ((tick_delay * step as f32).round() as u16).max(2);
+        let mut fits = true;
+        loop {
+            let mut gif_frame = frame.to_gif_frame();
+            gif_frame.delay = delay;
+            encoder.write_frame(&gif_frame)?;
+            if encoder.get_ref().len() > max_bytes {
+                fits = false;
+                break;
+            }
+
+            if game.turn() >= end || game.is_finished() {
+                break;
+            }
+            for _ in 0..step {
+                game.step();
+            }
+            frame = draw(&game);
+        }
+
+        if fits {
+            return Ok(Some(encoder.into_inner()?));
+        }
+        step *= 2;
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,232 @@
+use std::fmt;
+use serde::de::{Deserialize, SeqAccess, Visitor, Error};
+
+// since replays are cached
+// might want to optimize for space
+
+// u16 can hold up to 2^16 = 65535. 50 * 50 (max size of map) < 65535
+pub type TileIndex = u16;
+
+pub type PlayerIndex = u8;
+
+/// Turns are counted in half-turns ("ticks"), same as the generals.io server.
+pub type Turn = u32;
+
+#[derive(Clone, Default)]
+pub struct Options {
+    pub speed: f32,
+}
+
+impl<'de> Deserialize<'de> for Options {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+            D: serde::Deserializer<'de> {
+
+        struct OptionsVisitor;
+        impl<'de> Visitor<'de> for OptionsVisitor {
+            type Value = Options;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("options struct")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                    A: serde::de::SeqAccess<'de>, {
+                let speed = seq.next_element::<f32>()?.ok_or(A::Error::custom("options missing speed"))?;
+                // terrain densities only matter for generating the map
+                while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+                Ok(Options { speed })
+            }
+        }
+
+        deserializer.deserialize_seq(OptionsVisitor)
+
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Move {
+    pub player_index: PlayerIndex,
+    pub start: TileIndex,
+    pub end: TileIndex,
+    pub is50: bool,
+    pub turn: Turn,
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+            D: serde::Deserializer<'de> {
+
+        struct MoveVisitor;
+        impl<'de> Visitor<'de> for MoveVisitor {
+            type Value = Move;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("move struct")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                    A: serde::de::SeqAccess<'de>, {
+                Ok(Move {
+                    player_index: seq.next_element()?.ok_or(A::Error::custom("missing player_index"))?,
+                    start: seq.next_element()?.ok_or(A::Error::custom("missing start"))?,
+                    end: seq.next_element::<TileIndex>()?.ok_or(A::Error::custom("missing end"))?,
+                    is50: seq.next_element::<bool>()?.ok_or(A::Error::custom("missing is50"))?,
+                    turn: seq.next_element::<Turn>()?.ok_or(A::Error::custom("missing turn"))?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(MoveVisitor)
+    
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Surrender {
+    pub index: usize,
+    pub turn: Turn,
+}
+
+impl<'de> Deserialize<'de> for Surrender {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+            D: serde::Deserializer<'de> {
+        struct SurrenderVisitor;
+        impl<'de> Visitor<'de> for SurrenderVisitor {
+            type Value = Surrender;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("surrender struct")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                    A: serde::de::SeqAccess<'de>, {
+                Ok(Surrender {
+                    index: seq.next_element()?.ok_or(A::Error::custom("missing index"))?,
+                    turn: seq.next_element()?.ok_or(A::Error::custom("missing turn"))?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(SurrenderVisitor)
+    
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ChatMessage {
+    pub message: String,
+    pub prefix: String,
+    pub player_index: PlayerIndex,
+    pub turn: Turn,
+}
+
+impl<'de> Deserialize <'de> for ChatMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+            D: serde::Deserializer<'de> {
+        struct ChatVisitor;
+        impl<'de> Visitor<'de> for ChatVisitor {
+            type Value = ChatMessage;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "chat message")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                    A: SeqAccess<'de>, {
+                Ok(ChatMessage {
+                    message: seq.next_element()?.ok_or(A::Error::custom("missing message"))?,
+                    prefix: seq.next_element()?.ok_or(A::Error::custom("missing prefix"))?,
+                    player_index: seq.next_element()?.ok_or(A::Error::custom("missing player_index"))?,
+                    turn: seq.next_element()?.ok_or(A::Error::custom("missing turn"))?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(ChatVisitor)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Replay {
+    pub id: String,
+    pub map_width: u32,
+    pub map_height: u32,
+    pub usernames: Vec<String>,
+    pub cities: Vec<TileIndex>,
+    pub city_armies: Vec<u32>,
+    pub generals: Vec<TileIndex>,
+    pub mountains: Vec<TileIndex>,
+    pub moves: Vec<Move>,
+    pub afks: Vec<Surrender>,
+    pub teams: Option<Vec<u32>>,
+    pub map_title: Option<String>,
+    pub neutrals: Vec<TileIndex>,
+    pub neutral_armies: Vec<u32>,
+    pub swamps: Vec<TileIndex>,
+    pub chat: Vec<ChatMessage>,
+    pub player_colors: Vec<u32>,
+    pub options: Options
+}
+
+impl<'de> Deserialize<'de> for Replay {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+            D: serde::Deserializer<'de> {
+        struct ReplayVisitor;
+        impl<'de> Visitor<'de> for ReplayVisitor {
+            type Value = Replay;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "replay")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                    A: SeqAccess<'de>, {
+                seq.next_element::<serde::de::IgnoredAny>()?.ok_or(A::Error::custom("missing version"))?;
+                let id = seq.next_element()?.ok_or(A::Error::custom("missing id"))?;
+                let map_width = seq.next_element()?.ok_or(A::Error::custom("missing map_width"))?;
+                let map_height = seq.next_element()?.ok_or(A::Error::custom("missing map_height"))?;
+                let usernames: Vec<String> = seq.next_element()?.ok_or(A::Error::custom("missing usernames"))?;
+                seq.next_element::<serde::de::IgnoredAny>()?.ok_or(A::Error::custom("missing stars"))?;
+                let cities = seq.next_element()?.ok_or(A::Error::custom("missing cities"))?;
+                let city_armies = seq.next_element()?.ok_or(A::Error::custom("missing city_armies"))?;
+                let generals = seq.next_element()?.ok_or(A::Error::custom("missing generals"))?;
+                let mountains = seq.next_element()?.ok_or(A::Error::custom("missing mountains"))?;
+                let moves = seq.next_element()?.ok_or(A::Error::custom("missing moves"))?;
+                let afks = seq.next_element()?.ok_or(A::Error::custom("missing afks"))?;
+
+                // everything after this was added in later replay versions,
+                // and may be missing or null
+                let teams = seq.next_element::<Option<_>>()?.flatten();
+                let map_title = seq.next_element::<Option<_>>()?.flatten();
+                let neutrals = seq.next_element::<Option<_>>()?.flatten().unwrap_or_default();
+                let neutral_armies = seq.next_element::<Option<_>>()?.flatten().unwrap_or_default();
+                let swamps = seq.next_element::<Option<_>>()?.flatten().unwrap_or_default();
+                let chat = seq.next_element::<Option<_>>()?.flatten().unwrap_or_default();
+                let player_colors = seq.next_element::<Option<_>>()?.flatten()
+                    .unwrap_or_else(|| (0..usernames.len() as u32).collect());
+                seq.next_element::<serde::de::IgnoredAny>()?;
+                let options = seq.next_element::<Option<_>>()?.flatten().unwrap_or_default();
+
+                // ignore anything newer
+                while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+
+                Ok(Replay {
+                    id, map_width, map_height, usernames, cities, city_armies, generals, mountains,
+                    moves, afks, teams, map_title, neutrals, neutral_armies, swamps, chat, player_colors, options
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(ReplayVisitor)
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-player statistics over a simulated replay
+
+use super::{game::Game, replay::{Replay, PlayerIndex, Turn}};
+
+#[derive(Clone, Default)]
+pub struct PlayerStats {
+    /// Total army at the start of every full turn.
+    pub army: Vec<u64>,
+    /// Land at the start of every full turn.
+    pub land: Vec<u32>,
+    pub peak_army: u64,
+    /// Most cities (not counting generals) held at once.
+    pub peak_cities: u32,
+    pub moves: u32,
+    /// Full turns alive without making a move.
+    pub idle_turns: u32,
+    /// Full turns alive.
+    pub turns_alive: u32,
+    /// First full turn bordering an enemy.
+    pub first_contact: Option<Turn>,
+    /// Full turn the player lost their general.
+    pub eliminated: Option<Turn>,
+    pub killer: Option<PlayerIndex>,
+    /// Full turn the player went AFK or surrendered.
+    pub afk: Option<Turn>,
+}
+
+impl PlayerStats {
+    pub fn moves_per_turn(&self) -> f64 {
+        if self.turns_alive == 0 { 0.0 } else { self.moves as f64 / self.turns_alive as f64 }
+    }
+}
+
+/// Simulates the whole replay. Turns are full turns, not ticks.
+pub fn compute(replay: &Replay) -> Vec<PlayerStats> {
+    simulate(replay).0
+}
+
+/// Like [`compute`], also returning the game at its end so callers that draw
+/// the final board don't simulate the replay twice.
+pub fn simulate(replay: &Replay) -> (Vec<PlayerStats>, Game<'_>) {
+    let players = replay.usernames.len();
+    let mut stats = vec![PlayerStats::default(); players];
+    let mut moved = vec![false; players];
+
+    for afk in &replay.afks {
+        if let Some(s) = stats.get_mut(afk.index) {
+            s.afk = s.afk.or(Some(afk.turn / 2));
+        }
+    }
+
+    let mut game = Game::new(replay);
+    let mut move_index = 0;
+    loop {
+        let turn = game.turn();
+        while let Some(m) = replay.moves.get(move_index) {
+            if m.turn > turn { break; }
+            if let (Some(moved), Some(s)) = (moved.get_mut(m.player_index as usize), stats.get_mut(m.player_index as usize)) {
+                *moved = true;
+                s.moves += 1;
+            }
+            move_index += 1;
+        }
+
+        if turn.is_multiple_of(2) {
+            let board = game.board();
+            for (p, s) in stats.iter_mut().enumerate() {
+                let p = p as PlayerIndex;
+                let army = board.army(p);
+                s.army.push(army);
+                s.land.push(board.land(p));
+                s.peak_army = s.peak_army.max(army);
+                s.peak_cities = s.peak_cities.max(board.cities(p));
+
+                if game.is_alive(p) {
+                    s.turns_alive += 1;
+                }
+                if s.first_contact.is_none() && borders_enemy(&game, p) {
+                    s.first_contact = Some(turn / 2);
+                }
+            }
+        }
+
+        if game.is_finished() {
+            break;
+        }
+        game.step();
+
+        // a full turn just ended
+        if game.turn().is_multiple_of(2) {
+            for (p, s) in stats.iter_mut().enumerate() {
+                if game.is_alive(p as PlayerIndex) && !moved[p] {
+                    s.idle_turns += 1;
+                }
+            }
+            moved.iter_mut().for_each(|m| *m = false);
+        }
+    }
+
+    for kill in game.kills() {
+        if let Some(s) = stats.get_mut(kill.victim as usize) {
+            s.eliminated = Some(kill.turn / 2);
+            s.killer = Some(kill.killer);
+        }
+    }
+
+    (stats, game)
+}
+
+fn borders_enemy(game: &Game, player: PlayerIndex) -> bool {
+    let board = game.board();
+    let (w, h) = (board.width as usize, board.height as usize);
+    board.tiles.iter().enumerate()
+        .filter(|(_, t)| t.owner == Some(player))
+        .any(|(i, _)| {
+            let (x, y) = (i % w, i / w);
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < w).then(|| i + 1),
+                (y > 0).then(|| i - w),
+                (y + 1 < h).then(|| i + w),
+            ];
+            neighbors.iter().flatten().any(|&n| {
+                matches!(board.tiles[n].owner, Some(other) if !game.is_teammate(other, player))
+            })
+        })
+}
+
+/// Player indices ordered by finish, best first: players who lasted longer
+/// place higher, and survivors are ordered by final land.
+pub fn placements(stats: &[PlayerStats]) -> Vec<PlayerIndex> {
+    let out = |s: &PlayerStats| match (s.eliminated, s.afk) {
+        (Some(a), Some(b)) => a.min(b),
+        (Some(t), None) | (None, Some(t)) => t,
+        (None, None) => Turn::MAX,
+    };
+    let mut order = (0..stats.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&p| std::cmp::Reverse((out(&stats[p]), stats[p].land.last().copied().unwrap_or(0))));
+    order.into_iter().map(|p| p as PlayerIndex).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulate;
+    use crate::replay::{lzstring, replay::Replay};
+
+    fn fixture() -> Replay {
+        let json = lzstring::decompress_from_u8(include_bytes!("fixtures/capture.gior")).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn capture() {
+        let replay = fixture();
+        let (stats, game) = simulate(&replay);
+        assert_eq!(game.turn(), 12);
+        assert_eq!(stats.iter().map(|s| s.moves).collect::<Vec<_>>(), vec![1, 1]);
+        assert_eq!((stats[1].eliminated, stats[1].killer), (Some(5), Some(0)));
+        assert_eq!(stats[0].army.len(), 7);
+    }
+
+    #[test]
+    fn unsorted_moves_finish() {
+        let mut replay = fixture();
+        replay.moves.swap(0, 1);
+        let (stats, game) = simulate(&replay);
+        assert!(game.is_finished());
+        assert_eq!(game.turn(), 12);
+        assert_eq!(stats.iter().map(|s| s.moves).collect::<Vec<_>>(), vec![1, 1]);
+    }
+}
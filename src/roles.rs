@@ -0,0 +1,87 @@
+//! Discord roles from star tiers
+
+use std::{collections::{HashMap, HashSet}, time::Duration};
+
+use serenity::{http::Http, model::{guild::Member, id::{GuildId, RoleId}}};
+
+use crate::{stars::{self, Mode, Stars}, DB};
+
+/// Delay between members, since each one is a generals.io request.
+const MEMBER_DELAY: Duration = Duration::from_secs(1);
+
+/// Members with at least `stars` in `mode` get `role`.
+#[derive(Clone, Copy, Debug)]
+pub struct RoleTier {
+    pub role: u64,
+    pub mode: Mode,
+    pub stars: f64,
+    pub alltime: bool,
+}
+
+/// Roles a player with `stars` has earned: only the highest qualifying
+/// tier counts for each mode.
+pub fn earned_roles(tiers: &[RoleTier], stars: &Stars) -> HashSet<u64> {
+    let mut best: HashMap<(String, bool), &RoleTier> = HashMap::new();
+    for tier in tiers {
+        if stars.get(tier.mode, tier.alltime).is_some_and(|s| s >= tier.stars) {
+            let entry = best.entry((tier.mode.to_string(), tier.alltime)).or_insert(tier);
+            if tier.stars > entry.stars {
+                *entry = tier;
+            }
+        }
+    }
+    best.values().map(|tier| tier.role).collect()
+}
+
+/// Adds and removes tier roles on `member`, logging every change.
+pub async fn sync_member(http: &Http, guild: GuildId, member: &Member, username: &str, tiers: &[RoleTier]) -> crate::Result<()> {
+    if tiers.is_empty() {
+        return Ok(());
+    }
+
+    let earned = earned_roles(tiers, &stars::fetch(username).await?);
+    for tier in tiers {
+        let has = member.roles.contains(&RoleId(tier.role));
+        if earned.contains(&tier.role) && !has {
+            http.add_member_role(guild.0, member.user.id.0, tier.role).await?;
+            eprintln!("roles: added {} to {} ({}) in {}", tier.role, member.user.id, username, guild);
+        } else if !earned.contains(&tier.role) && has {
+            http.remove_member_role(guild.0, member.user.id.0, tier.role).await?;
+            eprintln!("roles: removed {} from {} ({}) in {}", tier.role, member.user.id, username, guild);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes every tier role from `member`, for when they unregister.
+pub async fn clear_member(http: &Http, guild: GuildId, member: &Member, tiers: &[RoleTier]) -> crate::Result<()> {
+    for tier in tiers.iter().filter(|t| member.roles.contains(&RoleId(t.role))) {
+        http.remove_member_role(guild.0, member.user.id.0, tier.role).await?;
+        eprintln!("roles: removed {} from unregistered {} in {}", tier.role, member.user.id, guild);
+    }
+
+    Ok(())
+}
+
+/// Syncs tier roles for every registered member of `guild`.
+pub async fn sync_guild(http: &Http, guild: GuildId) -> crate::Result<()> {
+    let db = DB.get().await;
+    let tiers = db.get_role_tiers(guild.0).await?;
+    if tiers.is_empty() {
+        return Ok(());
+    }
+
+    let usernames = db.get_users().await?.into_iter().collect::<HashMap<_, _>>();
+    let members = crate::guild_members(http, guild).await?;
+    for member in members {
+        if let Some(username) = usernames.get(&member.user.id.0) {
+            if let Err(e) = sync_member(http, guild, &member, username, &tiers).await.map_err(|e| e.to_string()) {
+                eprintln!("roles: failed to sync {} in {}: {}", member.user.id, guild, e);
+            }
+            tokio::time::sleep(MEMBER_DELAY).await;
+        }
+    }
+
+    Ok(())
+}
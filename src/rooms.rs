@@ -0,0 +1,125 @@
+//! Custom game rooms and links to them
+
+use std::fmt;
+
+use rand::Rng;
+
+#[derive(Clone, Copy)]
+pub enum Speed {
+    P25,
+    P5,
+    P75,
+    S1,
+    S1P5,
+    S2,
+    S3,
+    S4
+}
+
+#[derive(Debug)]
+pub struct SpeedError(pub String);
+
+impl std::error::Error for SpeedError {}
+impl fmt::Display for SpeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x", self.as_num())
+    }
+}
+
+impl Speed {
+    fn as_num(self) -> &'static str {
+        match self {
+            Speed::P25 => "0.25",
+            Speed::P5 => "0.5",
+            Speed::P75 => "0.75",
+            Speed::S1 => "1",
+            Speed::S1P5 => "1.5",
+            Speed::S2 => "2",
+            Speed::S3 => "3",
+            Speed::S4 => "4"
+        }
+    }
+}
+
+impl std::str::FromStr for Speed {
+    type Err = SpeedError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "0.25" | "0.25x" => Speed::P25,
+            "0.5" | "0.5x" => Speed::P5,
+            "0.75" | "0.75x" => Speed::P75,
+            "1" | "1.0" | "1x" | "1.0x" => Speed::S1,
+            "1.5" | "1.5x" => Speed::S1P5,
+            "2" | "2.0" | "2x" | "2.0x" => Speed::S2,
+            "3" | "3.0" | "3x" | "3.0x" => Speed::S3,
+            "4" | "4.0" | "4x" | "4.0x" => Speed::S4,
+            _ => return Err(SpeedError(format!("invalid speed '{}'", s)))
+        })
+    }
+}
+
+/// A custom game room created through the bot.
+pub struct Room {
+    pub code: String,
+    pub map: Option<String>,
+    pub speed: Option<Speed>,
+    /// Discord ID of whoever created the room.
+    pub creator: u64,
+    /// Seconds since the epoch.
+    pub time: u64,
+}
+
+impl Room {
+    pub fn url(&self) -> String {
+        room_url(&self.code, self.map.as_deref(), self.speed)
+    }
+
+    /// Room details, one per line.
+    pub fn description(&self) -> String {
+        let mut description = self.url();
+        if let Some(map) = &self.map {
+            description += &format!("\n**Map:** {}", map);
+        }
+        if let Some(speed) = self.speed {
+            description += &format!("\n**Speed:** {}", speed);
+        }
+        description
+    }
+}
+
+/// Random four character room code.
+pub fn random_code() -> String {
+    std::iter::repeat(())
+        .map(|_| rand::thread_rng().sample(rand::distributions::Alphanumeric))
+        .map(char::from)
+        .take(4)
+        .collect()
+}
+
+/// Link to the custom room `code`, or to a queue for `main`, `1v1` and `2v2`.
+pub fn room_url(code: &str, map: Option<&str>, speed: Option<Speed>) -> String {
+    let mut url = match code {
+        "main" => "https://generals.io/?queue=main".to_string(),
+        "1v1" => "https://generals.io/?queue=1v1".to_string(),
+        "2v2" => "https://generals.io/teams/matchmaking".to_string(),
+        _ => format!("https://generals.io/games/{}", urlencoding::encode(code))
+    };
+    let mut options = Vec::new();
+    if let Some(map) = map {
+        options.push(format!("map={}", urlencoding::encode(map)));
+    }
+    if let Some(speed) = speed {
+        options.push(format!("speed={}", speed.as_num()));
+    }
+    if !options.is_empty() {
+        url += if url.contains('?') { "&" } else { "?" };
+        url += &options.join("&");
+    }
+    url
+}
@@ -0,0 +1,80 @@
+//! Seasonal leagues, archived when they end
+
+use std::collections::HashSet;
+
+use serenity::{http::Http, model::id::GuildId};
+
+use crate::{ratings::{self, Pool}, DB};
+
+pub struct Season {
+    pub guild: u64,
+    /// Numbered from 1 within each guild.
+    pub number: u32,
+    pub name: String,
+    /// Seconds since the epoch.
+    pub start: u64,
+    pub end: u64,
+    /// Whether ratings are soft reset when the season starts.
+    pub soft_reset: bool,
+    pub archived: bool,
+}
+
+/// Final server rating of a player in an archived season.
+pub struct SeasonRating {
+    pub username: String,
+    pub rating: f64,
+    pub rd: f64,
+    /// Games played during the season.
+    pub games: u32,
+}
+
+/// Snapshots the stars of registered members and every rating pool into the
+/// season archive.
+pub async fn archive(http: &Http, season: &Season) -> crate::Result<()> {
+    let db = DB.get().await;
+    let member_ids = crate::guild_members(http, GuildId(season.guild)).await?
+        .into_iter()
+        .map(|m| m.user.id.0)
+        .collect::<HashSet<_>>();
+    let members = db.get_users().await?
+        .into_iter()
+        .filter(|(discord, _)| member_ids.contains(discord))
+        .map(|(_, username)| username)
+        .collect::<HashSet<_>>();
+
+    // the latest snapshot before the season ended
+    let times = db.get_snapshot_times(u32::MAX).await?;
+    let stars = match times.into_iter().find(|&t| t <= season.end) {
+        Some(time) => db.get_stars_at(time).await?,
+        None => Vec::new(),
+    };
+    let stars = stars.into_iter().filter(|(u, _)| members.contains(u)).collect::<Vec<_>>();
+
+    // ratings as they stood when the season ended
+    let resets = db.get_seasons(season.guild).await?
+        .into_iter()
+        .filter(|s| s.soft_reset && s.start <= season.end)
+        .map(|s| s.start)
+        .collect::<Vec<_>>();
+    let mut standings = Vec::new();
+    for pool in [Pool::Duel, Pool::Ffa, Pool::Teams].iter().copied() {
+        let games = db.get_rated_games(season.guild, pool).await?
+            .into_iter()
+            .filter(|g| g.started <= season.end)
+            .collect::<Vec<_>>();
+        let pool_standings = ratings::compute(&games, &resets)
+            .into_iter()
+            .filter_map(|(username, history)| {
+                let username = members.iter().find(|u| u.to_lowercase() == username)?.clone();
+                let games = history.iter().filter(|&&(t, _)| t >= season.start).count() as u32;
+                let rating = history.last()?.1;
+                (games > 0).then_some(SeasonRating { username, rating: rating.rating, rd: rating.rd, games })
+            })
+            .collect::<Vec<_>>();
+        standings.push((pool, pool_standings));
+    }
+
+    db.archive_season(season.guild, season.number, &stars, &standings).await?;
+    eprintln!("seasons: archived season {} in {}", season.number, season.guild);
+    Ok(())
+}
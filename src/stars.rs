@@ -0,0 +1,107 @@
+//! generals.io star ratings
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Ffa,
+    Duel,
+    M2v2,
+}
+
+#[derive(Debug)]
+pub struct ModeError(pub String);
+
+impl std::error::Error for ModeError {}
+impl fmt::Display for ModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Mode::Ffa => "FFA",
+            Mode::Duel => "1v1",
+            Mode::M2v2 => "2v2",
+        })
+    }
+}
+
+impl FromStr for Mode {
+    type Err = ModeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "ffa" => Mode::Ffa,
+            "duel" | "1v1" => Mode::Duel,
+            "2v2" => Mode::M2v2,
+            _ => return Err(ModeError(format!("invalid mode '{}'", s)))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Stars {
+    pub ffa: Option<f64>,
+    pub m1v1: Option<f64>,
+    pub m2v2: Option<f64>,
+
+    pub ffa_alltime: Option<f64>,
+    pub m1v1_alltime: Option<f64>,
+    pub m2v2_alltime: Option<f64>,
+}
+
+impl Stars {
+    pub fn get(&self, mode: Mode, alltime: bool) -> Option<f64> {
+        match (mode, alltime) {
+            (Mode::Ffa, false) => self.ffa,
+            (Mode::Duel, false) => self.m1v1,
+            (Mode::M2v2, false) => self.m2v2,
+            (Mode::Ffa, true) => self.ffa_alltime,
+            (Mode::Duel, true) => self.m1v1_alltime,
+            (Mode::M2v2, true) => self.m2v2_alltime,
+        }
+    }
+}
+
+/// Fetches current and all-time stars from `starsAndRanks`.
+pub async fn fetch(username: &str) -> crate::Result<Stars> {
+    #[derive(Serialize, Deserialize)]
+    struct RawStars {
+        ffa: Option<String>,
+        #[serde(rename = "duel")]
+        m1v1: Option<String>,
+        #[serde(rename = "2v2")]
+        m2v2: Option<String>,
+
+        #[serde(rename = "ffa-alltime")]
+        ffa_alltime: Option<String>,
+        #[serde(rename = "duel-alltime")]
+        m1v1_alltime: Option<String>,
+        #[serde(rename = "2v2-alltime")]
+        m2v2_alltime: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct StarsAndRanks {
+        stars: RawStars,
+    }
+
+    let resp: StarsAndRanks = reqwest::get(format!("https://generals.io/api/starsAndRanks?u={}", urlencoding::encode(username)))
+        .await?
+        .json()
+        .await?;
+
+    let parse = |x: Option<String>| x.map(|x| x.parse::<f64>()).transpose();
+    Ok(Stars {
+        ffa: parse(resp.stars.ffa)?,
+        m1v1: parse(resp.stars.m1v1)?,
+        m2v2: parse(resp.stars.m2v2)?,
+        ffa_alltime: parse(resp.stars.ffa_alltime)?,
+        m1v1_alltime: parse(resp.stars.m1v1_alltime)?,
+        m2v2_alltime: parse(resp.stars.m2v2_alltime)?,
+    })
+}
@@ -0,0 +1,403 @@
+//! Single- and double-elimination brackets
+//!
+//! Only seeds and reported results are stored; the bracket itself is
+//! rebuilt from them whenever it is needed.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::replay::render::Canvas;
+use super::{Format, FormatError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Side {
+    Winners,
+    Losers,
+    Final,
+    Swiss,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MatchId {
+    pub side: Side,
+    /// One-based.
+    pub round: u32,
+    /// Zero-based, top to bottom.
+    pub position: u32,
+}
+
+impl MatchId {
+    fn new(side: Side, round: u32, position: u32) -> MatchId {
+        MatchId { side, round, position }
+    }
+}
+
+impl fmt::Display for MatchId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.side {
+            Side::Winners => "W",
+            Side::Losers => "L",
+            Side::Final => "F",
+            Side::Swiss => "S",
+        };
+        write!(f, "{}{}-{}", side, self.round, self.position + 1)
+    }
+}
+
+impl FromStr for MatchId {
+    type Err = FormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || FormatError(format!("invalid match '{}'", s));
+        let side = match s.chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some('W') => Side::Winners,
+            Some('L') => Side::Losers,
+            Some('F') => Side::Final,
+            Some('S') => Side::Swiss,
+            _ => return Err(err()),
+        };
+        let (round, position) = s[1..].split_once('-').ok_or_else(err)?;
+        let round = round.parse().map_err(|_| err())?;
+        let position = position.parse::<u32>().map_err(|_| err())?.checked_sub(1).ok_or_else(err)?;
+        Ok(MatchId::new(side, round, position))
+    }
+}
+
+/// Who fills a spot in a match.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Slot {
+    /// Index into the seeded entrants.
+    Player(usize),
+    /// Nobody, so the opponent advances automatically.
+    Bye,
+    /// Decided by a match that hasn't been played yet.
+    Pending,
+}
+
+pub struct Match {
+    pub id: MatchId,
+    pub a: Slot,
+    pub b: Slot,
+    pub winner: Slot,
+    pub loser: Slot,
+    /// Matches whose winners fill `a` and `b`, for drawing.
+    sources: [Option<MatchId>; 2],
+}
+
+impl Match {
+    pub(super) fn new(id: MatchId, a: Slot, b: Slot, winner: Slot, loser: Slot) -> Match {
+        Match { id, a, b, winner, loser, sources: [None, None] }
+    }
+
+    /// Whether both players are known and the result isn't.
+    pub fn is_playable(&self) -> bool {
+        matches!((self.a, self.b, self.winner), (Slot::Player(_), Slot::Player(_), Slot::Pending))
+    }
+
+    pub fn has(&self, player: usize) -> bool {
+        self.has_slot(Slot::Player(player))
+    }
+
+    pub fn has_slot(&self, slot: Slot) -> bool {
+        self.a == slot || self.b == slot
+    }
+}
+
+pub struct Bracket {
+    pub format: Format,
+    /// In order of rounds, winners bracket first.
+    pub matches: Vec<Match>,
+    pub champion: Slot,
+    winners_rounds: u32,
+    losers_rounds: u32,
+}
+
+/// Bracket positions of seeds so that the top seeds meet as late as possible.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let n = order.len() * 2;
+        order = order.into_iter().flat_map(|s| [s, n - 1 - s]).collect();
+    }
+    order
+}
+
+/// Winner and loser of a match between `a` and `b`, given the reported winner.
+pub(super) fn resolve(a: Slot, b: Slot, result: Option<usize>) -> (Slot, Slot) {
+    match (a, b) {
+        (Slot::Pending, _) | (_, Slot::Pending) => (Slot::Pending, Slot::Pending),
+        (Slot::Player(_), Slot::Bye) | (Slot::Bye, Slot::Bye) => (a, b),
+        (Slot::Bye, Slot::Player(_)) => (b, a),
+        (Slot::Player(x), Slot::Player(y)) => match result {
+            Some(w) if w == x => (a, b),
+            Some(w) if w == y => (b, a),
+            _ => (Slot::Pending, Slot::Pending),
+        },
+    }
+}
+
+impl Bracket {
+    /// Builds the bracket for `players` seeded entrants, with `results`
+    /// mapping matches to the index of their winner.
+    pub fn build(format: Format, players: usize, results: &HashMap<MatchId, usize>) -> Bracket {
+        let size = players.next_power_of_two().max(2);
+        let winners_rounds = size.trailing_zeros();
+        let losers_rounds = if format == Format::Double { 2 * (winners_rounds - 1) } else { 0 };
+        let mut bracket = Bracket { format, matches: Vec::new(), champion: Slot::Pending, winners_rounds, losers_rounds };
+
+        let push = |bracket: &mut Bracket, id: MatchId, a: Slot, b: Slot, sources: [Option<MatchId>; 2]| {
+            let (winner, loser) = resolve(a, b, results.get(&id).copied());
+            bracket.matches.push(Match { id, a, b, winner, loser, sources });
+        };
+        let get = |bracket: &Bracket, id: MatchId| -> (Slot, Slot) {
+            let m = bracket.matches.iter().find(|m| m.id == id).unwrap();
+            (m.winner, m.loser)
+        };
+
+        let order = seed_order(size);
+        for p in 0..size / 2 {
+            let slot = |s: usize| if s < players { Slot::Player(s) } else { Slot::Bye };
+            push(&mut bracket, MatchId::new(Side::Winners, 1, p as u32), slot(order[2 * p]), slot(order[2 * p + 1]), [None, None]);
+        }
+        for round in 2..=winners_rounds {
+            for p in 0..(size >> round) as u32 {
+                let (sa, sb) = (MatchId::new(Side::Winners, round - 1, 2 * p), MatchId::new(Side::Winners, round - 1, 2 * p + 1));
+                let (a, b) = (get(&bracket, sa).0, get(&bracket, sb).0);
+                push(&mut bracket, MatchId::new(Side::Winners, round, p), a, b, [Some(sa), Some(sb)]);
+            }
+        }
+        let winners_final = MatchId::new(Side::Winners, winners_rounds, 0);
+
+        if format == Format::Single {
+            bracket.champion = get(&bracket, winners_final).0;
+            return bracket;
+        }
+
+        // losers rounds alternate between pairing up survivors (odd) and
+        // survivors facing players dropping down from the winners bracket (even)
+        for round in 1..=losers_rounds {
+            let k = round.div_ceil(2);
+            let count = (size >> (k + 1)) as u32;
+            for p in 0..count {
+                let id = MatchId::new(Side::Losers, round, p);
+                if round == 1 {
+                    let a = get(&bracket, MatchId::new(Side::Winners, 1, 2 * p)).1;
+                    let b = get(&bracket, MatchId::new(Side::Winners, 1, 2 * p + 1)).1;
+                    push(&mut bracket, id, a, b, [None, None]);
+                } else if round.is_multiple_of(2) {
+                    // alternate the drop order to delay rematches
+                    let q = if k % 2 == 1 { count - 1 - p } else { p };
+                    let sa = MatchId::new(Side::Losers, round - 1, p);
+                    let a = get(&bracket, sa).0;
+                    let b = get(&bracket, MatchId::new(Side::Winners, k + 1, q)).1;
+                    push(&mut bracket, id, a, b, [Some(sa), None]);
+                } else {
+                    let (sa, sb) = (MatchId::new(Side::Losers, round - 1, 2 * p), MatchId::new(Side::Losers, round - 1, 2 * p + 1));
+                    let (a, b) = (get(&bracket, sa).0, get(&bracket, sb).0);
+                    push(&mut bracket, id, a, b, [Some(sa), Some(sb)]);
+                }
+            }
+        }
+        let losers_champion = match losers_rounds {
+            0 => (get(&bracket, winners_final).1, None),
+            _ => {
+                let id = MatchId::new(Side::Losers, losers_rounds, 0);
+                (get(&bracket, id).0, Some(id))
+            }
+        };
+
+        let final_id = MatchId::new(Side::Final, 1, 0);
+        let a = get(&bracket, winners_final).0;
+        push(&mut bracket, final_id, a, losers_champion.0, [Some(winners_final), losers_champion.1]);
+        let (winner, loser) = get(&bracket, final_id);
+        bracket.champion = winner;
+        // the winners bracket champion gets a second chance if they lose the final
+        if winner != Slot::Pending && winner == losers_champion.0 && a != Slot::Bye {
+            let reset = MatchId::new(Side::Final, 2, 0);
+            push(&mut bracket, reset, winner, loser, [Some(final_id), None]);
+            bracket.champion = get(&bracket, reset).0;
+        }
+
+        bracket
+    }
+
+    /// A bracket of already paired `rounds` rounds, such as Swiss rounds.
+    pub(super) fn from_rounds(format: Format, matches: Vec<Match>, champion: Slot, rounds: u32) -> Bracket {
+        Bracket { format, matches, champion, winners_rounds: rounds, losers_rounds: 0 }
+    }
+
+    pub fn get(&self, id: MatchId) -> Option<&Match> {
+        self.matches.iter().find(|m| m.id == id)
+    }
+
+    /// The match `player` has to play next, if any.
+    pub fn next_match(&self, player: usize) -> Option<&Match> {
+        self.matches.iter().find(|m| m.is_playable() && m.has(player))
+    }
+
+    /// Human-readable name for a round.
+    pub fn round_name(&self, side: Side, round: u32) -> String {
+        match side {
+            Side::Winners if round == self.winners_rounds => "Final".to_string(),
+            Side::Winners if round + 1 == self.winners_rounds => "Semifinals".to_string(),
+            Side::Winners if self.format == Format::Double => format!("Winners Round {}", round),
+            Side::Winners => format!("Round {}", round),
+            Side::Losers if round == self.losers_rounds => "Losers Final".to_string(),
+            Side::Losers => format!("Losers Round {}", round),
+            Side::Final if round == 1 => "Grand Final".to_string(),
+            Side::Final => "Grand Final Reset".to_string(),
+            Side::Swiss => format!("Round {}", round),
+        }
+    }
+
+    /// Rounds in display order as (side, round).
+    pub fn rounds(&self) -> Vec<(Side, u32)> {
+        let mut rounds = Vec::new();
+        for m in &self.matches {
+            if !rounds.contains(&(m.id.side, m.id.round)) {
+                rounds.push((m.id.side, m.id.round));
+            }
+        }
+        rounds
+    }
+}
+
+const COLUMN_WIDTH: u32 = 90;
+const BOX_WIDTH: u32 = 60;
+const ROW_HEIGHT: u32 = 18;
+/// Vertical space per first round match.
+const MATCH_SPACING: u32 = 48;
+const MARGIN: u32 = 16;
+
+const BACKGROUND: [u8; 3] = [0x2f, 0x31, 0x36];
+const LINE: [u8; 3] = [0x72, 0x76, 0x7d];
+const PENDING: [u8; 3] = [0x40, 0x44, 0x4b];
+const WON: [u8; 3] = [0x3b, 0xa5, 0x5d];
+const LOST: [u8; 3] = [0x5c, 0x2b, 0x2e];
+const TEXT: [u8; 3] = [0xff, 0xff, 0xff];
+
+/// Draws the bracket with seeds in place of names: the winners bracket on
+/// top, the losers bracket below it and the grand final on the right.
+pub fn draw(bracket: &Bracket) -> Canvas {
+    let first_round = bracket.matches.iter().filter(|m| m.id.side == Side::Winners && m.id.round == 1).count() as u32;
+    let winners_height = first_round * MATCH_SPACING;
+    let losers_first = bracket.matches.iter().filter(|m| m.id.side == Side::Losers && m.id.round == 1).count() as u32;
+    let losers_height = losers_first * MATCH_SPACING;
+
+    let column = |id: MatchId| match id.side {
+        Side::Winners => id.round - 1,
+        Side::Losers => id.round - 1,
+        Side::Final => bracket.winners_rounds.max(bracket.losers_rounds) + id.round - 1,
+        Side::Swiss => id.round - 1,
+    };
+    let columns = bracket.matches.iter().map(|m| column(m.id) + 1).max().unwrap_or(1);
+    let width = 2 * MARGIN + columns * COLUMN_WIDTH;
+    let height = 2 * MARGIN + winners_height + if losers_height > 0 { MARGIN + losers_height } else { 0 };
+
+    // centre of each match box
+    let count = |side: Side, round: u32| bracket.matches.iter().filter(|m| m.id.side == side && m.id.round == round).count() as u32;
+    let centre = |id: MatchId| -> (u32, u32) {
+        let x = MARGIN + column(id) * COLUMN_WIDTH + BOX_WIDTH / 2;
+        let (top, band) = match id.side {
+            Side::Winners => (MARGIN, winners_height),
+            Side::Losers => (2 * MARGIN + winners_height, losers_height),
+            Side::Final | Side::Swiss => (MARGIN, winners_height),
+        };
+        let n = count(id.side, id.round).max(1);
+        (x, top + (2 * id.position + 1) * band / (2 * n))
+    };
+
+    let mut canvas = Canvas::new(width, height);
+    canvas.fill_rect(0, 0, width, height, BACKGROUND);
+
+    for m in &bracket.matches {
+        let (x, y) = centre(m.id);
+        for source in m.sources.iter().flatten() {
+            let (sx, sy) = centre(*source);
+            let (sx, x0) = ((sx + BOX_WIDTH / 2) as i64, (x - BOX_WIDTH / 2) as i64);
+            let mid = (sx + x0) / 2;
+            canvas.draw_line((sx, sy as i64), (mid, sy as i64), 1, LINE);
+            canvas.draw_line((mid, sy as i64), (mid, y as i64), 1, LINE);
+            canvas.draw_line((mid, y as i64), (x0, y as i64), 1, LINE);
+        }
+    }
+
+    for m in &bracket.matches {
+        let (x, y) = centre(m.id);
+        for (k, &slot) in [m.a, m.b].iter().enumerate() {
+            let top = y - ROW_HEIGHT + k as u32 * ROW_HEIGHT;
+            let colour = match (slot, m.winner) {
+                (Slot::Bye, _) | (_, Slot::Pending) => PENDING,
+                (s, w) if s == w => WON,
+                _ => LOST,
+            };
+            canvas.fill_rect(x - BOX_WIDTH / 2, top, BOX_WIDTH, ROW_HEIGHT - 1, colour);
+            if let Slot::Player(p) = slot {
+                canvas.draw_number(p as u64 + 1, x, top + ROW_HEIGHT / 2, BOX_WIDTH - 8, TEXT);
+            }
+        }
+    }
+
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Bracket, MatchId, Side, Slot};
+    use crate::tournament::Format;
+
+    fn results(reported: &[(&str, usize)]) -> HashMap<MatchId, usize> {
+        reported.iter().map(|&(id, winner)| (id.parse().unwrap(), winner)).collect()
+    }
+
+    fn players(bracket: &Bracket, id: &str) -> (Slot, Slot) {
+        let m = bracket.get(id.parse().unwrap()).unwrap();
+        (m.a, m.b)
+    }
+
+    #[test]
+    fn match_ids_round_trip() {
+        let id = "w2-1".parse::<MatchId>().unwrap();
+        assert_eq!(id, MatchId { side: Side::Winners, round: 2, position: 0 });
+        assert_eq!(id.to_string(), "W2-1");
+        assert!("L1-0".parse::<MatchId>().is_err());
+        assert!("X1-1".parse::<MatchId>().is_err());
+    }
+
+    #[test]
+    fn single_elimination_with_bye() {
+        let bracket = Bracket::build(Format::Single, 3, &HashMap::new());
+        // the top seed gets the bye and waits for the winner of 2 vs 3
+        assert_eq!(players(&bracket, "W1-1"), (Slot::Player(0), Slot::Bye));
+        assert_eq!(players(&bracket, "W1-2"), (Slot::Player(1), Slot::Player(2)));
+        assert_eq!(players(&bracket, "W2-1"), (Slot::Player(0), Slot::Pending));
+        assert!(bracket.next_match(0).is_none());
+        assert_eq!(bracket.next_match(2).map(|m| m.id.to_string()), Some("W1-2".to_string()));
+        assert_eq!(bracket.champion, Slot::Pending);
+
+        let bracket = Bracket::build(Format::Single, 3, &results(&[("W1-2", 2), ("W2-1", 2)]));
+        assert_eq!(players(&bracket, "W2-1"), (Slot::Player(0), Slot::Player(2)));
+        assert_eq!(bracket.champion, Slot::Player(2));
+    }
+
+    #[test]
+    fn double_elimination_with_reset() {
+        let played = [("W1-1", 0), ("W1-2", 1), ("W2-1", 0), ("L1-1", 2), ("L2-1", 1)];
+        let bracket = Bracket::build(Format::Double, 4, &results(&played));
+        assert_eq!(players(&bracket, "L1-1"), (Slot::Player(3), Slot::Player(2)));
+        assert_eq!(players(&bracket, "L2-1"), (Slot::Player(2), Slot::Player(1)));
+        assert_eq!(players(&bracket, "F1-1"), (Slot::Player(0), Slot::Player(1)));
+        assert_eq!(bracket.round_name(Side::Losers, 2), "Losers Final");
+
+        // the losers bracket champion has to win twice
+        let bracket = Bracket::build(Format::Double, 4, &results(&[&played[..], &[("F1-1", 1)]].concat()));
+        assert_eq!(players(&bracket, "F2-1"), (Slot::Player(1), Slot::Player(0)));
+        assert_eq!(bracket.champion, Slot::Pending);
+        let bracket = Bracket::build(Format::Double, 4, &results(&[&played[..], &[("F1-1", 1), ("F2-1", 0)]].concat()));
+        assert_eq!(bracket.champion, Slot::Player(0));
+
+        let bracket = Bracket::build(Format::Double, 4, &results(&[&played[..], &[("F1-1", 0)]].concat()));
+        assert!(bracket.get("F2-1".parse().unwrap()).is_none());
+        assert_eq!(bracket.champion, Slot::Player(0));
+    }
+}
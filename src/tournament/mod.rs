@@ -0,0 +1,151 @@
+//! Tournaments among registered users
+
+pub mod bracket;
+pub mod swiss;
+
+use std::{fmt, str::FromStr};
+
+use crate::{replay::{metadata::Metadata, replay::Replay}, stars::Mode};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Single,
+    Double,
+    Swiss,
+}
+
+#[derive(Debug)]
+pub struct FormatError(pub String);
+
+impl std::error::Error for FormatError {}
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Format::Single => "single",
+            Format::Double => "double",
+            Format::Swiss => "swiss",
+        })
+    }
+}
+
+impl Format {
+    /// Human-readable name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Single => "single elimination",
+            Format::Double => "double elimination",
+            Format::Swiss => "Swiss",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = FormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "single" | "single elimination" => Format::Single,
+            "double" | "double elimination" => Format::Double,
+            "swiss" => Format::Swiss,
+            _ => return Err(FormatError(format!("invalid format '{}'", s)))
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum State {
+    Registration,
+    Running,
+    Finished,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            State::Registration => "registration",
+            State::Running => "running",
+            State::Finished => "finished",
+        })
+    }
+}
+
+impl FromStr for State {
+    type Err = FormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "registration" => State::Registration,
+            "running" => State::Running,
+            "finished" => State::Finished,
+            _ => return Err(FormatError(format!("invalid state '{}'", s)))
+        })
+    }
+}
+
+pub struct Tournament {
+    pub id: u64,
+    pub guild: u64,
+    pub name: String,
+    pub format: Format,
+    /// Mode whose stars are used for seeding.
+    pub mode: Mode,
+    pub state: State,
+    /// Swiss rounds to play, decided when the tournament starts if zero.
+    pub rounds: u32,
+    pub creator: u64,
+    /// Seconds since the epoch.
+    pub created: u64,
+}
+
+/// A registered user taking part in a tournament.
+#[derive(Clone)]
+pub struct Entrant {
+    pub discord: u64,
+    pub username: String,
+    /// Stars when the tournament started, used for seeding.
+    pub stars: f64,
+}
+
+/// A reported match result.
+pub struct MatchResult {
+    /// [`bracket::MatchId`] as text.
+    pub id: String,
+    /// Discord ID of the winner.
+    pub winner: u64,
+    pub replay: Option<String>,
+}
+
+/// A match with an agreed time and custom room, whose result is read from
+/// the players' replays.
+pub struct ScheduledMatch {
+    pub tournament: u64,
+    /// [`bracket::MatchId`] as text.
+    pub id: String,
+    /// Channel the result is posted in.
+    pub channel: u64,
+    /// Seconds since the epoch.
+    pub time: u64,
+    pub code: String,
+    /// Whether a result was recorded or the match stopped being watched.
+    pub done: bool,
+}
+
+/// Custom games between exactly `a` and `b` that started after `time`
+/// (seconds since the epoch), oldest first. Replay listings don't say which
+/// room a game was played in, so these still need [`played_in`] checking.
+pub fn candidate_games<'a>(games: &'a [Metadata], a: &'a str, b: &'a str, time: u64) -> impl Iterator<Item=&'a Metadata> {
+    games.iter()
+        .rev()
+        .filter(move |g| g.type_ == "custom" && g.started / 1000 >= time && g.ranking.len() == 2 && g.placement(a).is_some() && g.placement(b).is_some())
+}
+
+/// Whether a player typed the room `code` in `replay`'s chat, which ties the
+/// game to the scheduled room.
+pub fn played_in(replay: &Replay, code: &str) -> bool {
+    let code = code.to_lowercase();
+    replay.chat.iter().any(|c| (c.player_index as usize) < replay.usernames.len() && c.message.to_lowercase().contains(&code))
+}
@@ -0,0 +1,176 @@
+//! Swiss-system pairings and standings
+//!
+//! Like elimination brackets, pairings are rebuilt from seeds and results:
+//! each round is paired from the standings after the rounds before it.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{bracket::{resolve, Bracket, Match, MatchId, Side, Slot}, Format};
+
+/// Most recursive steps spent looking for pairings without rematches.
+const MAX_PAIRING_STEPS: u32 = 100_000;
+
+#[derive(Clone, Copy, Default)]
+pub struct Standing {
+    /// Index into the seeded entrants.
+    pub player: usize,
+    /// Wins, counting byes.
+    pub score: u32,
+    /// Sum of opponents' scores.
+    pub buchholz: u32,
+    /// Sum of beaten opponents' scores.
+    pub sonneborn_berger: u32,
+    pub byes: u32,
+}
+
+/// Standings after the finished `matches`, best first. Ties are broken by
+/// Buchholz, then Sonneborn-Berger, then seed.
+pub fn standings(players: usize, matches: &[Match]) -> Vec<Standing> {
+    let mut standings = (0..players).map(|player| Standing { player, ..Default::default() }).collect::<Vec<_>>();
+    let mut opponents = vec![Vec::new(); players];
+    for m in matches {
+        match (m.winner, m.loser) {
+            (Slot::Player(w), Slot::Player(l)) => {
+                standings[w].score += 1;
+                opponents[w].push((l, true));
+                opponents[l].push((w, false));
+            },
+            (Slot::Player(w), Slot::Bye) => {
+                standings[w].score += 1;
+                standings[w].byes += 1;
+            },
+            _ => {}
+        }
+    }
+
+    let scores = standings.iter().map(|s| s.score).collect::<Vec<_>>();
+    for (s, opponents) in standings.iter_mut().zip(&opponents) {
+        s.buchholz = opponents.iter().map(|&(o, _)| scores[o]).sum();
+        s.sonneborn_berger = opponents.iter().filter(|&&(_, won)| won).map(|&(o, _)| scores[o]).sum();
+    }
+    standings.sort_by_key(|s| (std::cmp::Reverse((s.score, s.buchholz, s.sonneborn_berger)), s.player));
+    standings
+}
+
+/// Pairs `order` top to bottom, giving each player the highest-ranked
+/// opponent they haven't played yet.
+fn pair(order: &[usize], played: &HashSet<(usize, usize)>, steps: &mut u32) -> Option<Vec<(usize, usize)>> {
+    let (&first, rest) = match order.split_first() {
+        Some(split) => split,
+        None => return Some(Vec::new()),
+    };
+    for (k, &opponent) in rest.iter().enumerate() {
+        *steps += 1;
+        if *steps > MAX_PAIRING_STEPS {
+            return None;
+        }
+        if played.contains(&(first, opponent)) {
+            continue;
+        }
+        let remaining = rest.iter().enumerate().filter(|&(j, _)| j != k).map(|(_, &p)| p).collect::<Vec<_>>();
+        if let Some(mut pairs) = pair(&remaining, played, steps) {
+            pairs.insert(0, (first, opponent));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// Builds every round that can be paired so far for `players` seeded
+/// entrants, with `results` mapping matches to the index of their winner.
+pub fn build(players: usize, rounds: u32, results: &HashMap<MatchId, usize>) -> (Bracket, Vec<Standing>) {
+    let mut matches: Vec<Match> = Vec::new();
+    let mut finished = true;
+    for round in 1..=rounds {
+        let standings = standings(players, &matches);
+        let mut order = standings.iter().map(|s| s.player).collect::<Vec<_>>();
+
+        // the lowest-ranked player who hasn't had a bye sits out
+        let bye = if order.len() % 2 == 1 {
+            let k = standings.iter().rposition(|s| s.byes == 0).unwrap_or(order.len() - 1);
+            Some(order.remove(k))
+        } else {
+            None
+        };
+
+        let mut played = HashSet::new();
+        for m in &matches {
+            if let (Slot::Player(a), Slot::Player(b)) = (m.a, m.b) {
+                played.insert((a, b));
+                played.insert((b, a));
+            }
+        }
+        // fall back to rematches rather than not pairing at all
+        let pairs = pair(&order, &played, &mut 0)
+            .unwrap_or_else(|| order.chunks(2).map(|c| (c[0], c[1])).collect());
+
+        let pairs = pairs.into_iter()
+            .map(|(a, b)| (Slot::Player(a), Slot::Player(b)))
+            .chain(bye.map(|p| (Slot::Player(p), Slot::Bye)));
+        for (position, (a, b)) in pairs.enumerate() {
+            let id = MatchId { side: Side::Swiss, round, position: position as u32 };
+            let (winner, loser) = resolve(a, b, results.get(&id).copied());
+            finished &= winner != Slot::Pending;
+            matches.push(Match::new(id, a, b, winner, loser));
+        }
+
+        if !finished {
+            break;
+        }
+    }
+
+    let standings = standings(players, &matches);
+    let champion = match (finished, standings.first()) {
+        (true, Some(s)) => Slot::Player(s.player),
+        _ => Slot::Pending,
+    };
+    (Bracket::from_rounds(Format::Swiss, matches, champion, rounds), standings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::build;
+    use crate::tournament::bracket::{Bracket, MatchId, Slot};
+
+    fn results(reported: &[(&str, usize)]) -> HashMap<MatchId, usize> {
+        reported.iter().map(|&(id, winner)| (id.parse().unwrap(), winner)).collect()
+    }
+
+    fn pairings(bracket: &Bracket, round: u32) -> Vec<(Slot, Slot)> {
+        bracket.matches.iter().filter(|m| m.id.round == round).map(|m| (m.a, m.b)).collect()
+    }
+
+    #[test]
+    fn pairs_without_rematches() {
+        let (bracket, _) = build(4, 3, &HashMap::new());
+        assert_eq!(pairings(&bracket, 1), vec![(Slot::Player(0), Slot::Player(1)), (Slot::Player(2), Slot::Player(3))]);
+        // later rounds wait for results
+        assert!(pairings(&bracket, 2).is_empty());
+
+        let (bracket, _) = build(4, 3, &results(&[("S1-1", 0), ("S1-2", 2), ("S2-1", 0), ("S2-2", 1)]));
+        assert_eq!(pairings(&bracket, 2), vec![(Slot::Player(0), Slot::Player(2)), (Slot::Player(1), Slot::Player(3))]);
+        // 0 has played 1 and 2 already
+        assert_eq!(pairings(&bracket, 3), vec![(Slot::Player(0), Slot::Player(3)), (Slot::Player(1), Slot::Player(2))]);
+        assert_eq!(bracket.champion, Slot::Pending);
+    }
+
+    #[test]
+    fn standings_break_ties() {
+        let played = [("S1-1", 0), ("S1-2", 2), ("S2-1", 0), ("S2-2", 1), ("S3-1", 0), ("S3-2", 2)];
+        let (bracket, standings) = build(4, 3, &results(&played));
+        let table = standings.iter().map(|s| (s.player, s.score, s.buchholz, s.sonneborn_berger)).collect::<Vec<_>>();
+        assert_eq!(table, vec![(0, 3, 3, 3), (2, 2, 4, 1), (1, 1, 5, 0), (3, 0, 6, 0)]);
+        assert_eq!(bracket.champion, Slot::Player(0));
+    }
+
+    #[test]
+    fn byes_rotate() {
+        let (bracket, _) = build(3, 2, &results(&[("S1-1", 0)]));
+        // the bottom seed sits out first and scores the bye as a win
+        assert_eq!(pairings(&bracket, 1), vec![(Slot::Player(0), Slot::Player(1)), (Slot::Player(2), Slot::Bye)]);
+        assert_eq!(bracket.get("S1-2".parse().unwrap()).map(|m| m.winner), Some(Slot::Player(2)));
+        assert_eq!(pairings(&bracket, 2), vec![(Slot::Player(0), Slot::Player(2)), (Slot::Player(1), Slot::Bye)]);
+    }
+}